@@ -0,0 +1,153 @@
+use std::collections::HashMap;
+use std::io::{self, Write};
+
+use crate::report::Report;
+use crate::{check_execution, Data, ResultFile};
+
+fn process_usage(result: &ResultFile) -> HashMap<String, u64> {
+    let mut usage = HashMap::new();
+    for execution in &result.executions {
+        *usage.entry(execution.process_name.clone()).or_insert(0) += execution.count;
+    }
+    usage
+}
+
+/* Pareto dominance on (objective up, makespan down); an invalid result never dominates */
+fn dominates(a: &Report, b: &Report) -> bool {
+    if !a.is_valid() {
+        return false;
+    }
+    if !b.is_valid() {
+        return true;
+    }
+    a.objective >= b.objective
+        && a.makespan <= b.makespan
+        && (a.objective > b.objective || a.makespan < b.makespan)
+}
+
+/* A stock missing from one side counts as 0 */
+fn same_stocks(a: &Report, b: &Report) -> bool {
+    a.final_stocks.keys().chain(b.final_stocks.keys())
+        .all(|stock| a.final_stocks.get(stock).unwrap_or(&0) == b.final_stocks.get(stock).unwrap_or(&0))
+}
+
+fn print_row(out: &mut impl Write, name: &str, a: u64, b: u64) -> io::Result<()> {
+    let delta = b as i128 - a as i128;
+    let marker = if delta == 0 { "" } else { "  *" };
+    writeln!(out, "  {:<28} {:>14} {:>14} {:>+14}{}", name, a, b, delta, marker)
+}
+
+/// Validates both results against the same file and prints how they compare.
+/// Returns whether both results are valid.
+pub fn run(data: &Data, first: (&str, &ResultFile), second: (&str, &ResultFile), out: &mut impl Write) -> io::Result<bool> {
+    let (first_name, first_result) = first;
+    let (second_name, second_result) = second;
+    let a = check_execution(data, first_result, None);
+    let b = check_execution(data, second_result, None);
+
+    for (name, report) in [(first_name, &a), (second_name, &b)] {
+        writeln!(out, "\x1b[36m{}\x1b[0m", name)?;
+        write!(out, "{}", report.to_text())?;
+        writeln!(out)?;
+    }
+
+    writeln!(out, "  {:<28} {:>14} {:>14} {:>14}", "", "A", "B", "B - A")?;
+    print_row(out, "objective", a.objective, b.objective)?;
+    print_row(out, "makespan", a.makespan, b.makespan)?;
+
+    writeln!(out, "\nfinal stocks:")?;
+    let mut stocks: Vec<&String> = a.final_stocks.keys().chain(b.final_stocks.keys()).collect();
+    stocks.sort();
+    stocks.dedup();
+    for stock in stocks {
+        print_row(out, stock, *a.final_stocks.get(stock).unwrap_or(&0), *b.final_stocks.get(stock).unwrap_or(&0))?;
+    }
+
+    writeln!(out, "\nprocess usage:")?;
+    let usage_a = process_usage(first_result);
+    let usage_b = process_usage(second_result);
+    for process in &data.processes {
+        print_row(out, &process.id, *usage_a.get(&process.id).unwrap_or(&0), *usage_b.get(&process.id).unwrap_or(&0))?;
+    }
+
+    writeln!(out)?;
+    if dominates(&a, &b) {
+        writeln!(out, "A ({}) dominates B ({})", first_name, second_name)?;
+    } else if dominates(&b, &a) {
+        writeln!(out, "B ({}) dominates A ({})", second_name, first_name)?;
+    } else if !a.is_valid() && !b.is_valid() {
+        writeln!(out, "Neither dominates: both results are invalid")?;
+    } else if a.objective == b.objective && a.makespan == b.makespan && same_stocks(&a, &b) {
+        writeln!(out, "A and B are equivalent")?;
+    } else if a.objective == b.objective && a.makespan == b.makespan {
+        writeln!(out, "Neither dominates: same objective and makespan, different final stocks")?;
+    } else {
+        writeln!(out, "Neither dominates: objective and makespan trade off")?;
+    }
+
+    Ok(a.is_valid() && b.is_valid())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formats::parse_result_file;
+    use crate::tests::data;
+
+    fn report(result: &str) -> Report {
+        check_execution(&data(), &parse_result_file(result), None)
+    }
+
+    #[test]
+    fn strict_win_dominates() {
+        let (both, one) = (report("0:make_b:2\n5:make_c:2\n"), report("0:make_b\n5:make_c\n"));
+        assert_eq!((both.objective, both.makespan, one.objective, one.makespan), (2, 8, 1, 8));
+        assert!(dominates(&both, &one));
+        assert!(!dominates(&one, &both));
+    }
+
+    #[test]
+    fn tie_dominates_neither_way() {
+        let (a, b) = (report("0:make_b\n5:make_c\n"), report("0:make_b\n5:make_c\n"));
+        assert!(!dominates(&a, &b) && !dominates(&b, &a));
+        assert!(same_stocks(&a, &b));
+    }
+
+    #[test]
+    fn trade_off_is_incomparable() {
+        let (slow, fast) = (report("0:make_b\n5:make_c\n"), report("0:make_b\n"));
+        assert_eq!((slow.objective, slow.makespan, fast.objective, fast.makespan), (1, 8, 0, 5));
+        assert!(!dominates(&slow, &fast) && !dominates(&fast, &slow));
+    }
+
+    #[test]
+    fn invalid_result_never_dominates() {
+        let (invalid, idle) = (report("0:make_b:2\n5:make_c:3\n"), report(""));
+        assert!(!invalid.is_valid());
+        assert!(dominates(&idle, &invalid));
+        assert!(!dominates(&invalid, &idle));
+    }
+
+    #[test]
+    fn missing_stock_counts_as_zero() {
+        let a = Report { final_stocks: HashMap::from([("a".to_string(), 2), ("b".to_string(), 0)]), ..Report::default() };
+        let b = Report { final_stocks: HashMap::from([("a".to_string(), 2)]), ..Report::default() };
+        assert!(same_stocks(&a, &b) && same_stocks(&b, &a));
+        let c = Report { final_stocks: HashMap::from([("a".to_string(), 2), ("b".to_string(), 1)]), ..Report::default() };
+        assert!(!same_stocks(&c, &b) && !same_stocks(&b, &c));
+    }
+
+    #[test]
+    fn diff_output() {
+        let (first, second) = (parse_result_file("0:make_b\n5:make_c\n"), parse_result_file("0:make_b:2\n5:make_c:2\n"));
+        let mut out = Vec::new();
+        assert!(run(&data(), ("one", &first), ("two", &second), &mut out).unwrap());
+        let text = String::from_utf8(out).unwrap();
+        let row = |name: &str| text.lines().find(|line| line.split_whitespace().next() == Some(name)).unwrap().split_whitespace().collect::<Vec<_>>();
+        assert_eq!(row("objective"), ["objective", "1", "2", "+1", "*"]);
+        assert_eq!(row("makespan"), ["makespan", "8", "8", "+0"]);
+        assert_eq!(row("a"), ["a", "1", "0", "-1", "*"]);
+        assert_eq!(row("make_c"), ["make_c", "1", "2", "+1", "*"]);
+        assert_eq!(text.lines().last(), Some("B (two) dominates A (one)"));
+    }
+}
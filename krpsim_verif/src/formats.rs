@@ -0,0 +1,185 @@
+use std::collections::HashMap;
+
+use crate::json::{self, Value};
+use crate::report::{Violation, ViolationKind};
+use crate::{Execution, ResultFile};
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Format {
+    /// `time:process` lines, `Finished at time` and `Final stocks:` as written by krpsim
+    #[default]
+    Log,
+    /// `time:process` lines only, as in the subject; the final stocks are then
+    /// computed by the replay
+    Classic,
+    /// `time:process:count` lines
+    Compact,
+    /// `{"schedule":[{"time":..,"process":..,"count":..}],"finished_at":..,"final_stocks":{..}}`
+    Json,
+}
+
+impl Format {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Format::Log => "log",
+            Format::Classic => "classic",
+            Format::Compact => "compact",
+            Format::Json => "json",
+        }
+    }
+}
+
+/// JSON when the content opens an object or array, else by its lines: a
+/// `Final stocks:` section makes it a log, `time:process:count` lines make
+/// it compact.
+pub fn detect_format(content: &str) -> Format {
+    let trimmed = content.trim_start();
+    if trimmed.starts_with('{') || trimmed.starts_with('[') {
+        return Format::Json;
+    }
+    let mut compact = false;
+    for line in content.lines().map(str::trim) {
+        if line.starts_with("Final stocks:") {
+            return if compact { Format::Compact } else { Format::Log };
+        }
+        if !line.starts_with('#') && line.split(':').count() == 3 {
+            compact = true;
+        }
+    }
+    if compact { Format::Compact } else { Format::Classic }
+}
+
+pub fn parse_result_file(content: &str) -> ResultFile {
+    let format = detect_format(content);
+    let mut result = match format {
+        Format::Json => parse_json(content),
+        _ => parse_lines(content),
+    };
+    result.format = format;
+    result
+}
+
+fn malformed(line: Option<usize>, message: String) -> Violation {
+    Violation::new(line, ViolationKind::MalformedLine, message)
+}
+
+fn parse_lines(content: &str) -> ResultFile {
+    let mut result = ResultFile::default();
+    let mut final_stocks = HashMap::new();
+
+    let mut parsing_final_stocks = false;
+
+    for (index, trimmed) in content.lines().enumerate() {
+        let line_number = index + 1;
+        let trimmed = trimmed.trim();
+
+        if let Some(time) = trimmed.strip_prefix("Finished at time") {
+            match time.trim().parse::<u64>() {
+                Ok(time) => result.finished_at = Some(time),
+                Err(_) => result.malformed.push(malformed(Some(line_number), format!("invalid finish line '{}'", trimmed))),
+            }
+            continue;
+        }
+
+        if trimmed.starts_with("Final stocks:") {
+            parsing_final_stocks = true;
+            continue;
+        }
+
+        if trimmed.is_empty() || trimmed.starts_with('#') || trimmed.starts_with("no more process doable") {
+            continue;
+        }
+
+        let parts: Vec<&str> = trimmed.split(':').map(str::trim).collect();
+
+        if parsing_final_stocks {
+            match (parts.len(), parts.last().unwrap().parse::<u64>()) {
+                (2, Ok(stock_qty)) => {
+                    final_stocks.insert(parts[0].to_string(), (stock_qty, line_number));
+                }
+                _ => result.malformed.push(malformed(Some(line_number), format!("invalid final stock line '{}'", trimmed))),
+            }
+            continue;
+        }
+
+        let time = parts[0].parse::<u64>().ok();
+        let count = match parts.len() {
+            2 => Some(1),
+            3 => parts[2].parse::<u64>().ok(),
+            _ => None,
+        };
+        match (time, count) {
+            (Some(time), Some(count)) if !parts[1].is_empty() => {
+                /* a count of 0 starts nothing */
+                if count > 0 {
+                    result.executions.push(Execution { line: line_number, time, process_name: parts[1].to_string(), count });
+                }
+            }
+            _ => result.malformed.push(malformed(Some(line_number), format!("invalid execution line '{}'", trimmed))),
+        }
+    }
+
+    if parsing_final_stocks {
+        result.final_stocks = Some(final_stocks);
+    }
+
+    result
+}
+
+fn parse_json(content: &str) -> ResultFile {
+    let mut result = ResultFile::default();
+
+    let root = match json::parse(content) {
+        Ok(root) => root,
+        Err(err) => {
+            result.malformed.push(malformed(None, format!("invalid JSON: {}", err)));
+            return result;
+        }
+    };
+
+    let schedule = match (&root, root.get("schedule")) {
+        (Value::Array(entries), _) => entries.clone(),
+        (_, Some(Value::Array(entries))) => entries.clone(),
+        _ => {
+            result.malformed.push(malformed(root.line(), "missing \"schedule\" array".to_string()));
+            Vec::new()
+        }
+    };
+
+    for entry in &schedule {
+        let line = entry.line().unwrap_or(0);
+        let time = entry.get("time").and_then(Value::as_u64);
+        let process = entry.get("process").and_then(Value::as_str);
+        let count = entry.get("count").map_or(Some(1), Value::as_u64);
+        match (time, process, count) {
+            (Some(time), Some(process), Some(count)) => {
+                if count > 0 {
+                    result.executions.push(Execution { line, time, process_name: process.to_string(), count });
+                }
+            }
+            _ => result.malformed.push(malformed(Some(line), "schedule entry needs \"time\", \"process\" and an optional \"count\"".to_string())),
+        }
+    }
+
+    if let Some(finished_at) = root.get("finished_at") {
+        match finished_at.as_u64() {
+            Some(time) => result.finished_at = Some(time),
+            None => result.malformed.push(malformed(root.line(), "\"finished_at\" is not a cycle".to_string())),
+        }
+    }
+
+    if let Some(Value::Object(fields, line)) = root.get("final_stocks") {
+        let mut final_stocks = HashMap::new();
+        for (name, qty) in fields {
+            match qty.as_u64() {
+                Some(qty) => {
+                    final_stocks.insert(name.clone(), (qty, *line));
+                }
+                None => result.malformed.push(malformed(Some(*line), format!("final stock '{}' is not a quantity", name))),
+            }
+        }
+        result.final_stocks = Some(final_stocks);
+    }
+
+    result
+}
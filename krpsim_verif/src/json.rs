@@ -0,0 +1,202 @@
+/* Just enough JSON to read schedules back: no external crate needed */
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Value>),
+    Object(Vec<(String, Value)>, usize),
+}
+
+impl Value {
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        match self {
+            Value::Object(fields, _) => fields.iter().find(|(name, _)| name == key).map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    pub fn as_u64(&self) -> Option<u64> {
+        match self {
+            Value::Number(n) if *n >= 0.0 && n.fract() == 0.0 => Some(*n as u64),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /* Line of the opening brace, for objects */
+    pub fn line(&self) -> Option<usize> {
+        match self {
+            Value::Object(_, line) => Some(*line),
+            _ => None,
+        }
+    }
+}
+
+pub fn parse(source: &str) -> Result<Value, String> {
+    let mut parser = Parser { chars: source.chars().collect(), current: 0, line: 1 };
+    let value = parser.value()?;
+    parser.skip_whitespace();
+    if parser.current < parser.chars.len() {
+        return Err(format!("line {}: trailing characters after JSON value", parser.line));
+    }
+    Ok(value)
+}
+
+struct Parser {
+    chars: Vec<char>,
+    current: usize,
+    line: usize,
+}
+
+impl Parser {
+    fn value(&mut self) -> Result<Value, String> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some('{') => self.object(),
+            Some('[') => self.array(),
+            Some('"') => Ok(Value::String(self.string()?)),
+            Some('t') => self.keyword("true", Value::Bool(true)),
+            Some('f') => self.keyword("false", Value::Bool(false)),
+            Some('n') => self.keyword("null", Value::Null),
+            Some(c) if c == '-' || c.is_ascii_digit() => self.number(),
+            Some(c) => Err(format!("line {}: unexpected character '{}'", self.line, c)),
+            None => Err(format!("line {}: unexpected end of input", self.line)),
+        }
+    }
+
+    fn object(&mut self) -> Result<Value, String> {
+        let line = self.line;
+        let mut fields = Vec::new();
+        self.expect('{')?;
+        self.skip_whitespace();
+        if self.peek() == Some('}') {
+            self.current += 1;
+            return Ok(Value::Object(fields, line));
+        }
+        loop {
+            self.skip_whitespace();
+            let key = self.string()?;
+            self.skip_whitespace();
+            self.expect(':')?;
+            let value = self.value()?;
+            fields.push((key, value));
+            self.skip_whitespace();
+            match self.advance() {
+                Some(',') => continue,
+                Some('}') => return Ok(Value::Object(fields, line)),
+                _ => return Err(format!("line {}: expected ',' or '}}' in object", self.line)),
+            }
+        }
+    }
+
+    fn array(&mut self) -> Result<Value, String> {
+        let mut values = Vec::new();
+        self.expect('[')?;
+        self.skip_whitespace();
+        if self.peek() == Some(']') {
+            self.current += 1;
+            return Ok(Value::Array(values));
+        }
+        loop {
+            values.push(self.value()?);
+            self.skip_whitespace();
+            match self.advance() {
+                Some(',') => continue,
+                Some(']') => return Ok(Value::Array(values)),
+                _ => return Err(format!("line {}: expected ',' or ']' in array", self.line)),
+            }
+        }
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        let mut res = String::new();
+        self.expect('"')?;
+        loop {
+            match self.advance() {
+                Some('"') => return Ok(res),
+                Some('\\') => match self.advance() {
+                    Some('n') => res.push('\n'),
+                    Some('t') => res.push('\t'),
+                    Some('r') => res.push('\r'),
+                    Some('u') => {
+                        let code: String = (0..4).filter_map(|_| self.advance()).collect();
+                        let c = u32::from_str_radix(&code, 16).ok().and_then(char::from_u32)
+                            .ok_or(format!("line {}: invalid unicode escape", self.line))?;
+                        res.push(c);
+                    }
+                    Some(c) => res.push(c),
+                    None => return Err(format!("line {}: unterminated string", self.line)),
+                },
+                Some(c) => res.push(c),
+                None => return Err(format!("line {}: unterminated string", self.line)),
+            }
+        }
+    }
+
+    fn number(&mut self) -> Result<Value, String> {
+        let start = self.current;
+        while let Some(c) = self.peek() {
+            if c.is_ascii_digit() || "+-.eE".contains(c) {
+                self.current += 1;
+            } else {
+                break;
+            }
+        }
+        let text: String = self.chars[start..self.current].iter().collect();
+        text.parse::<f64>()
+            .map(Value::Number)
+            .map_err(|_| format!("line {}: invalid number '{}'", self.line, text))
+    }
+
+    fn keyword(&mut self, word: &str, value: Value) -> Result<Value, String> {
+        for expected in word.chars() {
+            if self.advance() != Some(expected) {
+                return Err(format!("line {}: invalid literal, expected '{}'", self.line, word));
+            }
+        }
+        Ok(value)
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), String> {
+        match self.advance() {
+            Some(c) if c == expected => Ok(()),
+            _ => Err(format!("line {}: expected '{}'", self.line, expected)),
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(c) = self.peek() {
+            if !c.is_whitespace() {
+                break;
+            }
+            if c == '\n' {
+                self.line += 1;
+            }
+            self.current += 1;
+        }
+    }
+
+    fn advance(&mut self) -> Option<char> {
+        let c = self.chars.get(self.current).cloned();
+        if c.is_some() {
+            self.current += 1;
+        }
+        if c == Some('\n') {
+            self.line += 1;
+        }
+        c
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.current).cloned()
+    }
+}
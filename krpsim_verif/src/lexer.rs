@@ -13,18 +13,18 @@ struct Lexer {
 pub fn lex(filename: &'static str) -> Result<Vec<Token>, io::Error> {
     let mut tokens: Vec<Token> = Vec::new();
     let lines = read_lines(filename)?;
-    for line in lines.flatten() {
+    for line in lines.map_while(Result::ok) {
         if line.starts_with("#") || line.starts_with("\n") {
             continue;
         }
         Lexer::new(line).tokenize(&mut tokens);
     }
-    return Ok(tokens);
+    Ok(tokens)
 }
 
 impl Lexer {
     fn new(source: String) -> Self {
-        return Self { source, current: 0 };
+        Self { source, current: 0 }
     }
 
     fn tokenize(&mut self, tokens: &mut Vec<Token>) {
@@ -51,12 +51,22 @@ impl Lexer {
             Token::Number(n) => self.current += (n.checked_ilog10().unwrap_or(0) + 1) as usize,
             Token::Time => self.current += 4,
             Token::Optimize => self.current += 8,
+            Token::Horizon => self.current += 7,
             _ => self.current += 1,
         }
         tokens.push(token);
         self.tokenize(tokens);
     }
 
+    /* `horizon` is only a keyword as a whole `horizon:<number>` line, so it
+     * stays a valid stock or process name everywhere else */
+    fn horizon_line(&self) -> bool {
+        let rest: String = self.source.chars().skip(self.current + "horizon".len()).collect();
+        let number = rest.trim_start().strip_prefix(':').map(str::trim);
+        self.source.chars().take(self.current).all(char::is_whitespace)
+            && number.is_some_and(|number| !number.is_empty() && number.chars().all(|c| c.is_ascii_digit()))
+    }
+
     fn identifier(&mut self, tokens: &mut Vec<Token>) {
         let ident: String = self
             .source
@@ -66,9 +76,10 @@ impl Lexer {
             .collect();
         match ident.as_str() {
             "optimize" => self.advance(Token::Optimize, tokens),
+            "horizon" if self.horizon_line() => self.advance(Token::Horizon, tokens),
             "time" => self.advance(Token::Time, tokens),
             "\n" => self.advance(Token::NewLine, tokens),
-            x if x.starts_with(|c: char| c.is_digit(10)) => match ident.parse::<u64>() {
+            x if x.starts_with(|c: char| c.is_ascii_digit()) => match ident.parse::<u64>() {
                 Ok(n) => self.advance(Token::Number(n), tokens),
                 _ => self.advance(Token::Invalid(ident), tokens),
             },
@@ -1,16 +1,22 @@
-#![allow(warnings)]
-use std::collections::HashMap;
-use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::Arc;
-use std::env;
+use std::collections::{BinaryHeap, HashMap};
+use std::cmp::Reverse;
 use clap::{Arg, ArgAction, Command};
 use std::path::PathBuf;
-use std::io::{self, BufRead};
 use std::fs::File;
+use std::io::{self, Write};
 
 mod lexer;
 mod parser;
 mod tokens;
+mod report;
+mod trace;
+mod json;
+mod formats;
+mod diff;
+
+use report::{Report, Violation, ViolationKind};
+use trace::Trace;
+use formats::Format;
 
 #[derive(Debug, Clone)]
 pub struct Process {
@@ -25,201 +31,511 @@ pub struct Data {
     pub stocks: HashMap<String, u64>,
     pub processes: Vec<Process>,
     pub objectives: Vec<String>,
+    pub horizon: Option<u64>,
 }
 
 #[derive(Debug)]
 pub struct Execution {
+    pub line: usize,
     pub time: u64,
     pub process_name: String,
+    /// Runs started at once, more than 1 only in compact and JSON results
+    pub count: u64,
+}
+
+#[derive(Debug, Default)]
+pub struct ResultFile {
+    pub executions: Vec<Execution>,
+    pub finished_at: Option<u64>,
+    pub final_stocks: Option<HashMap<String, (u64, usize)>>,
+    pub malformed: Vec<Violation>,
+    pub format: Format,
+}
+
+pub enum Mode {
+    Check { file: String, result_to_test: String, json: bool, trace: bool, csv: Option<String>, horizon: Option<u64> },
+    Diff { file: String, first: String, second: String },
 }
 
-fn get_args() -> (String, String) {
+fn get_args() -> Mode {
+    let path_arg = |name: &'static str, help: &'static str| {
+        Arg::new(name)
+            .help(help)
+            .required(true)
+            .value_parser(clap::value_parser!(PathBuf))
+    };
     let matches = Command::new("my_cli_app")
         .version("1.0")
         .about("Executes various algorithms on the provided file")
+        .args_conflicts_with_subcommands(true)
+        .subcommand_negates_reqs(true)
+        .arg(path_arg("file", "Path to the file"))
+        .arg(path_arg("result_to_test", "Path to the result to test"))
         .arg(
-            Arg::new("file")
-                .help("Path to the file")
-                .required(true)
-                .value_parser(clap::value_parser!(PathBuf)),
+            Arg::new("format")
+                .long("format")
+                .help("Output format of the report")
+                .value_parser(["text", "json"])
+                .default_value("text"),
         )
         .arg(
-            Arg::new("result_to_test")
-                .help("Path to the result to test")
-                .required(true)
+            Arg::new("trace")
+                .long("trace")
+                .help("Replay the result cycle by cycle, printing stock deltas and running processes (to stderr with --format json)")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("csv")
+                .long("csv")
+                .help("Export the stock levels over time to a CSV file")
                 .value_parser(clap::value_parser!(PathBuf)),
         )
+        .arg(
+            Arg::new("horizon")
+                .long("horizon")
+                .help("Cycle by which every run has to end, instead of the file's horizon line")
+                .value_parser(clap::value_parser!(u64)),
+        )
+        .subcommand(
+            Command::new("diff")
+                .about("Validates two results of the same file and compares them")
+                .arg(path_arg("file", "Path to the file"))
+                .arg(path_arg("first", "Path to the first result"))
+                .arg(path_arg("second", "Path to the second result")),
+        )
         .get_matches();
 
-    let file: PathBuf = matches.get_one::<PathBuf>("file").unwrap().clone();
+    let path = |matches: &clap::ArgMatches, name: &str| -> String {
+        matches.get_one::<PathBuf>(name).unwrap().to_string_lossy().to_string()
+    };
 
-    let result_to_test: PathBuf = matches.get_one::<PathBuf>("result_to_test").unwrap().clone();
+    if let Some(diff) = matches.subcommand_matches("diff") {
+        return Mode::Diff {
+            file: path(diff, "file"),
+            first: path(diff, "first"),
+            second: path(diff, "second"),
+        };
+    }
 
+    let json: bool = matches.get_one::<String>("format").is_some_and(|format| format == "json");
 
-    (file.to_string_lossy().to_string(), result_to_test.to_string_lossy().to_string())
-}
+    let trace: bool = matches.get_flag("trace");
 
-pub fn parse_result_file(lines: io::Lines<io::BufReader<File>>) -> (Vec<Execution>, HashMap<String, u64>) {
-    let mut executions = Vec::new();
-    let mut final_stocks = HashMap::new();
-
-    let mut parsing_final_stocks = false;
-
-    for line in lines {
-        match line {
-            Ok(trimmed) => {
-                let trimmed = trimmed.trim();
-
-                if trimmed.starts_with("Final stocks:") {
-                    parsing_final_stocks = true;
-                    continue;
-                }
-
-                if parsing_final_stocks {
-                    if !trimmed.is_empty() {
-                        let parts: Vec<&str> = trimmed.split(':').collect();
-                        if parts.len() == 2 {
-                            let stock_name = parts[0].trim().to_string();
-                            let stock_qty: u64 = parts[1].trim().parse().unwrap_or(0);
-                            final_stocks.insert(stock_name, stock_qty);
-                        }
-                        else {
-                            eprintln!("Invalid final stock line: {}", trimmed);
-                        }
-                    }
-                } else if !trimmed.is_empty() && trimmed.contains(':') {
-                    let parts: Vec<&str> = trimmed.split(':').collect();
-                    if parts.len() == 2 {
-                        let time: u64 = parts[0].trim().parse().unwrap_or(0);
-                        let process_name = parts[1].trim().to_string();
-                        executions.push(Execution { time, process_name });
-                    }
-                    else {
-                        eprintln!("Invalid execution line: {}", trimmed);
-                    }
-                }
-            }
-            Err(err) => {
-                eprintln!("Error reading line: {:?}", err);
-            }
-        }
-    }
+    let csv: Option<String> = matches.get_one::<PathBuf>("csv").map(|path| path.to_string_lossy().to_string());
 
-    if (!parsing_final_stocks) {
-        eprintln!("Final stocks not found in result file.");
-    }
+    let horizon: Option<u64> = matches.get_one::<u64>("horizon").cloned();
 
-    (executions, final_stocks)
+    Mode::Check {
+        file: path(&matches, "file"),
+        result_to_test: path(&matches, "result_to_test"),
+        json,
+        trace,
+        csv,
+        horizon,
+    }
 }
 
-pub fn check_execution(data: &Data, executions: &Vec<Execution>, final_stocks: &HashMap<String, u64>) -> Result<(), String> {
+/// Replays the executions under the concurrent semantics: a process consumes
+/// its inputs when it starts and its outputs only arrive at `start + time`.
+/// Runs may not end after the file's horizon, if it has one, and the objective
+/// is then the one reached at the horizon.
+/// Every problem is recorded; the replay goes on past a failing line.
+/// When a trace is given, the stocks are recorded at every cycle.
+pub fn check_execution(data: &Data, result: &ResultFile, mut trace: Option<&mut Trace>) -> Report {
+    let mut report = Report::default();
+    report.violations.extend(result.malformed.iter().cloned());
+
+    let executions = &result.executions;
     let mut current_stocks = data.stocks.clone();
-    let mut buffered_stocks = HashMap::new();
-    let mut previous_time = None;
+    let mut running: BinaryHeap<Reverse<(u64, usize)>> = BinaryHeap::new();
+    let mut previous_time = 0;
+    let mut last_completion = 0;
+    let mut at_horizon: Option<HashMap<String, u64>> = None;
 
-    for execution in executions {
+    for (index, execution) in executions.iter().enumerate() {
+        let line = Some(execution.line);
+
+        if let Some(horizon) = data.horizon.filter(|&horizon| execution.time > horizon && at_horizon.is_none()) {
+            at_horizon = Some(stocks_at(data, executions, &running, &current_stocks, horizon));
+        }
 
-        if Some(execution.time) != previous_time {
-            for (stock_name, qty) in buffered_stocks.drain() {
-                *current_stocks.entry(stock_name).or_insert(0) += qty;
+        if execution.time < previous_time {
+            report.push(line, ViolationKind::OutOfOrderTime, format!(
+                "time {} goes back before previous cycle {}", execution.time, previous_time
+            ));
+        }
+        if index == 0 || execution.time > previous_time {
+            if index > 0 {
+                record(trace.as_deref_mut(), executions, &running, &current_stocks, previous_time);
+            }
+            release_until(data, executions, &mut running, &mut current_stocks, execution.time, trace.as_deref_mut());
+        }
+        previous_time = previous_time.max(execution.time);
+
+        let Some(process) = data.processes.iter().find(|p| p.id == execution.process_name) else {
+            report.push(line, ViolationKind::UnknownProcess, format!(
+                "process '{}' not found at time {}", execution.process_name, execution.time
+            ));
+            continue;
+        };
+
+        let mut missing = false;
+        for (input_name, input_qty) in &process.input {
+            let stock_qty = current_stocks.get(input_name).cloned().unwrap_or(0);
+            let needed = input_qty.checked_mul(execution.count);
+            if needed.is_none_or(|needed| stock_qty < needed) {
+                report.push(line, ViolationKind::InsufficientStock, format!(
+                    "not enough stock for process '{}' x{} at time {}. Needed {} of {} per run, but only {} available",
+                    process.id, execution.count, execution.time, input_qty, input_name, stock_qty
+                ));
+                missing = true;
             }
-            previous_time = Some(execution.time);
+        }
+        if missing {
+            continue;
         }
 
-        let process = data.processes.iter().find(|p| p.id == execution.process_name);
-        if process.is_none() {
-            return Err(format!("Process '{}' not found at time {}.", execution.process_name, execution.time));
+        for (input_name, input_qty) in &process.input {
+            *current_stocks.get_mut(input_name).unwrap() -= input_qty * execution.count;
         }
 
-        if let Some(process) = process {
-            for (input_name, input_qty) in &process.input {
-                let stock_qty = current_stocks.get(input_name).cloned().unwrap_or(0);
-                if stock_qty < *input_qty {
-                    return Err(format!(
-                        "Not enough stock for process '{}' at time {}. Needed {} of {}, but only {} available.",
-                        process.id, execution.time, input_qty, input_name, stock_qty
-                    ));
-                }
+        let end = previous_time + process.time;
+        if let Some(horizon) = data.horizon.filter(|&horizon| end > horizon) {
+            report.push(line, ViolationKind::AfterHorizon, format!(
+                "process '{}' started at time {} ends at {}, after the horizon {}", process.id, execution.time, end, horizon
+            ));
+        }
+        last_completion = last_completion.max(end);
+        running.push(Reverse((end, index)));
+    }
+
+    if !executions.is_empty() {
+        record(trace.as_deref_mut(), executions, &running, &current_stocks, previous_time);
+    }
+    if let Some(horizon) = data.horizon.filter(|_| at_horizon.is_none()) {
+        at_horizon = Some(stocks_at(data, executions, &running, &current_stocks, horizon));
+    }
+    release_until(data, executions, &mut running, &mut current_stocks, u64::MAX, trace);
+
+    if let Some(claimed) = result.finished_at {
+        if claimed != last_completion {
+            report.push(None, ViolationKind::FinishMismatch, format!(
+                "finished at time {} is claimed, but the last process completes at time {}", claimed, last_completion
+            ));
+        }
+    }
+
+    if let Some(final_stocks) = &result.final_stocks {
+        let mut names: Vec<&String> = current_stocks.keys().chain(final_stocks.keys()).collect();
+        names.sort();
+        names.dedup();
+        for name in names {
+            let computed = *current_stocks.get(name).unwrap_or(&0);
+            let (claimed, line) = final_stocks.get(name).map_or((0, None), |&(qty, line)| (qty, Some(line)));
+            if computed != claimed {
+                report.push(line, ViolationKind::StockMismatch, format!(
+                    "final stock '{}' is {} but {} is claimed", name, computed, claimed
+                ));
             }
+        }
+    }
 
-            for (input_name, input_qty) in &process.input {
-                *current_stocks.get_mut(input_name).unwrap() -= input_qty;
+    report.violations.sort_by_key(|violation| violation.line.unwrap_or(usize::MAX));
+    let measured = at_horizon.as_ref().unwrap_or(&current_stocks);
+    report.objective = data.objectives.iter().map(|obj| *measured.get(obj).unwrap_or(&0)).sum();
+    report.makespan = last_completion;
+    report.final_stocks = current_stocks;
+    report
+}
+
+/* Adds the outputs of every running process that completes at or before `time`.
+ * Completions strictly before `time` are cycles of their own in the trace. */
+fn release_until(
+    data: &Data,
+    executions: &[Execution],
+    running: &mut BinaryHeap<Reverse<(u64, usize)>>,
+    current_stocks: &mut HashMap<String, u64>,
+    time: u64,
+    mut trace: Option<&mut Trace>,
+) {
+    while let Some(Reverse((end, _))) = running.peek().cloned() {
+        if end > time {
+            break;
+        }
+        while let Some(Reverse((next_end, index))) = running.peek().cloned() {
+            if next_end != end {
+                break;
             }
+            running.pop();
+            let process = data.processes.iter().find(|p| p.id == executions[index].process_name).unwrap();
             for (output_name, output_qty) in &process.output {
-                *buffered_stocks.entry(output_name.clone()).or_insert(0) += output_qty;
+                let stock = current_stocks.entry(output_name.clone()).or_insert(0);
+                *stock = stock.saturating_add(output_qty.saturating_mul(executions[index].count));
             }
-        } else {
-            return Err(format!("Process '{}' not found at time {}.", execution.process_name, execution.time));
+        }
+        if end < time {
+            record(trace.as_deref_mut(), executions, running, current_stocks, end);
         }
     }
+}
 
-    for (stock_name, qty) in buffered_stocks {
-        *current_stocks.entry(stock_name).or_insert(0) += qty;
+/* Stocks at `time`, once everything running has completed by then, with the
+ * replay itself left where it is */
+fn stocks_at(
+    data: &Data,
+    executions: &[Execution],
+    running: &BinaryHeap<Reverse<(u64, usize)>>,
+    current_stocks: &HashMap<String, u64>,
+    time: u64,
+) -> HashMap<String, u64> {
+    let mut stocks = current_stocks.clone();
+    for Reverse((_, index)) in running.iter().filter(|Reverse((end, _))| *end <= time) {
+        let process = data.processes.iter().find(|p| p.id == executions[*index].process_name).unwrap();
+        for (output_name, output_qty) in &process.output {
+            let stock = stocks.entry(output_name.clone()).or_insert(0);
+            *stock = stock.saturating_add(output_qty.saturating_mul(executions[*index].count));
+        }
     }
+    stocks
+}
 
-    if current_stocks == *final_stocks {
-        Ok(())
-    } else {
-        Err(format!(
-            "Final stocks do not match. Expected {:?}, but found {:?}.",
-            final_stocks, current_stocks
-        ))
+fn record(
+    trace: Option<&mut Trace>,
+    executions: &[Execution],
+    running: &BinaryHeap<Reverse<(u64, usize)>>,
+    current_stocks: &HashMap<String, u64>,
+    time: u64,
+) {
+    if let Some(trace) = trace {
+        let running = running.iter()
+            .map(|Reverse((end, index))| (executions[*index].process_name.clone(), *end, executions[*index].count))
+            .collect();
+        trace.record(time, current_stocks, running);
     }
 }
 
-fn main() {
-
-    let (file, result_to_test) = get_args();
-
-    /* PARSING */
-    let file_static: &'static str = Box::leak(file.clone().into_boxed_str());
+fn load_data(file: &str) -> Data {
+    let file_static: &'static str = Box::leak(file.to_string().into_boxed_str());
 
     let mut parser = parser::Parser::new(file_static);
-    match parser.parse() {
-        Err(err) => {
-            eprintln!("\n\nFATAL ERROR !!!!!: {:?}", err);
-            return;
-        }
-        _ => {}
-    };
-    let data = Data {
+    if let Err(err) = parser.parse() {
+        eprintln!("\n\nFATAL ERROR !!!!!: {:?}", err);
+        std::process::exit(2);
+    }
+    Data {
         stocks: parser.stocks,
         processes: parser.process,
         objectives: parser.optimize.unwrap(),
+        horizon: parser.horizon,
+    }
+}
+
+fn load_result(result_to_test: &str) -> ResultFile {
+    let content = match std::fs::read_to_string(result_to_test) {
+        Ok(content) => content,
+        Err(err) => {
+            eprintln!("Error opening result file {}: {:?}", result_to_test, err);
+            std::process::exit(2);
+        }
     };
 
-    let result_file_static: &'static str = Box::leak(file.clone().into_boxed_str());
+    formats::parse_result_file(&content)
+}
 
-    let result_file = match File::open(result_to_test) {
-        Ok(file) => file,
-        Err(err) => {
-            eprintln!("Error opening result file: {:?}", err);
-            return;
+fn main() {
+
+    let (file, result_to_test, json, show_trace, csv, horizon) = match get_args() {
+        Mode::Check { file, result_to_test, json, trace, csv, horizon } => (file, result_to_test, json, trace, csv, horizon),
+        Mode::Diff { file, first, second } => {
+            let data = load_data(&file);
+            let valid = diff::run(&data, (&first, &load_result(&first)), (&second, &load_result(&second)), &mut io::stdout())
+                .unwrap_or_else(|err| {
+                    eprintln!("Failed to print the comparison: {}", err);
+                    false
+                });
+            std::process::exit(if valid { 0 } else { 1 });
         }
     };
 
-    let result_file_lines = io::BufReader::new(result_file).lines();
-  
-    let (executions, final_stocks) = parse_result_file(result_file_lines);
+    /* PARSING */
+    let mut data = load_data(&file);
+    data.horizon = horizon.or(data.horizon);
 
-    println!("stocks: {:?}\n", data.stocks);
-    println!("processes:");
-    for p in &data.processes {
-        println!("{:?}", p);
-    }
-    println!();
-    println!("objectives: {:?}\n", data.objectives);
-    println!();
-    println!("executions:");
-    for e in &executions {
-        println!("{:?}", e);
+    let result = load_result(&result_to_test);
+
+    if !json {
+        println!("stocks: {:?}\n", data.stocks);
+        println!("processes:");
+        for p in &data.processes {
+            println!("{:?}", p);
+        }
+        println!();
+        println!("objectives: {:?}\n", data.objectives);
+        println!();
+        println!("executions:");
+        for e in &result.executions {
+            println!("{:?}", e);
+        }
+        match &result.final_stocks {
+            Some(final_stocks) => println!("final stocks: {:?}", final_stocks),
+            None => println!("final stocks: not given, computed from the executions"),
+        }
+        println!("format: {}", result.format.name());
+        println!();
     }
-    println!("final stocks: {:?}", final_stocks);   
-    println!();
     /**********************/
 
-    match check_execution(&data, &executions, &final_stocks) {
-        Ok(()) => println!("Execution is valid."),
-        Err(e) => println!("Execution is invalid: {}", e),
+    let mut trace = Trace::new(&data.stocks);
+    let report = check_execution(&data, &result, Some(&mut trace));
+
+    if show_trace {
+        /* stdout is left to the JSON report alone */
+        let printed = if json {
+            trace.print(&mut io::stderr()).and_then(|_| writeln!(io::stderr()))
+        } else {
+            trace.print(&mut io::stdout()).and_then(|_| writeln!(io::stdout()))
+        };
+        if let Err(err) = printed {
+            eprintln!("Failed to print the trace: {}", err);
+        }
+    }
+    if let Some(csv) = csv {
+        if let Err(err) = File::create(&csv).and_then(|mut file| trace.write_csv(&mut file)) {
+            eprintln!("Failed to write CSV file: {}", err);
+        }
+    }
+    if json {
+        print!("{}", report.to_json());
+    } else {
+        print!("{}", report.to_text());
+    }
+
+    if !report.is_valid() {
+        std::process::exit(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn process(id: &str, input: &[(&str, u64)], output: &[(&str, u64)], time: u64) -> Process {
+        let items = |list: &[(&str, u64)]| list.iter().map(|&(item, qty)| (item.to_string(), qty)).collect();
+        Process { id: id.to_string(), input: items(input), output: items(output), time }
+    }
+
+    /* a -> b in 5 cycles, b -> c in 3 */
+    pub(crate) fn data() -> Data {
+        Data {
+            stocks: HashMap::from([("a".to_string(), 2)]),
+            processes: vec![process("make_b", &[("a", 1)], &[("b", 1)], 5), process("make_c", &[("b", 1)], &[("c", 1)], 3)],
+            objectives: vec!["c".to_string()],
+            horizon: None,
+        }
+    }
+
+    fn check(data: &Data, result: &str) -> Report {
+        check_execution(data, &formats::parse_result_file(result), None)
+    }
+
+    fn kinds(report: &Report) -> Vec<ViolationKind> {
+        report.violations.iter().map(|violation| violation.kind).collect()
+    }
+
+    #[test]
+    fn valid_trace() {
+        let report = check(&data(), "0:make_b\n0:make_b\n5:make_c\n5:make_c\n");
+        assert!(report.is_valid(), "{}", report.to_text());
+        assert_eq!((report.objective, report.makespan), (2, 8));
+    }
+
+    #[test]
+    fn csv_starts_from_the_initial_stocks() {
+        let mut trace = Trace::new(&data().stocks);
+        check_execution(&data(), &formats::parse_result_file("0:make_b\n5:make_c\n"), Some(&mut trace));
+        let mut csv = Vec::new();
+        trace.write_csv(&mut csv).unwrap();
+        let rows: Vec<&str> = std::str::from_utf8(&csv).unwrap().lines().collect();
+        assert_eq!(rows[..3], ["time,a,b,c", "0,2,0,0", "0,1,0,0"]);
+    }
+
+    #[test]
+    fn outputs_arrive_after_the_delay() {
+        let report = check(&data(), "0:make_b\n4:make_c\n");
+        assert_eq!(kinds(&report), vec![ViolationKind::InsufficientStock]);
+    }
+
+    #[test]
+    fn out_of_order_time() {
+        let report = check(&data(), "5:make_b\n0:make_b\n");
+        assert_eq!(kinds(&report), vec![ViolationKind::OutOfOrderTime]);
+    }
+
+    #[test]
+    fn finish_mismatch() {
+        let report = check(&data(), "0:make_b\n\nFinished at time 4\n\nFinal stocks:\na:1\nb:1\n");
+        assert_eq!(kinds(&report), vec![ViolationKind::FinishMismatch]);
+    }
+
+    #[test]
+    fn unknown_process() {
+        let report = check(&data(), "0:make_d\n");
+        assert_eq!(kinds(&report), vec![ViolationKind::UnknownProcess]);
+    }
+
+    #[test]
+    fn insufficient_stock() {
+        let report = check(&data(), "0:make_b:3\n");
+        assert_eq!(kinds(&report), vec![ViolationKind::InsufficientStock]);
+    }
+
+    #[test]
+    fn stock_mismatch() {
+        let report = check(&data(), "0:make_b\n\nFinished at time 5\n\nFinal stocks:\na:1\nb:2\n");
+        assert_eq!(kinds(&report), vec![ViolationKind::StockMismatch]);
+    }
+
+    #[test]
+    fn after_horizon() {
+        let mut data = data();
+        data.horizon = Some(7);
+        let report = check(&data, "0:make_b\n5:make_c\n");
+        assert_eq!(kinds(&report), vec![ViolationKind::AfterHorizon]);
+        /* the run ending after the horizon does not count */
+        assert_eq!(report.objective, 0);
+    }
+
+    #[test]
+    fn malformed_line() {
+        let report = check(&data(), "0:make_b\nsoon:make_b\n");
+        assert_eq!(kinds(&report), vec![ViolationKind::MalformedLine]);
+        assert_eq!(report.violations[0].line, Some(2));
+    }
+
+    #[test]
+    fn every_violation_is_reported() {
+        let report = check(&data(), "5:make_b\n0:make_d\n0:make_c\n");
+        assert_eq!(kinds(&report), vec![
+            ViolationKind::OutOfOrderTime,
+            ViolationKind::UnknownProcess,
+            ViolationKind::OutOfOrderTime,
+            ViolationKind::InsufficientStock,
+        ]);
+    }
+
+    #[test]
+    fn json_report_round_trip() {
+        let report = check(&data(), "0:make_b\n0:make_\"d\"\n5:make_c\n");
+        let parsed = json::parse(&report.to_json()).unwrap();
+        assert_eq!(parsed.get("valid"), Some(&json::Value::Bool(false)));
+        assert_eq!(parsed.get("objective").and_then(json::Value::as_u64), Some(report.objective));
+        assert_eq!(parsed.get("makespan").and_then(json::Value::as_u64), Some(report.makespan));
+        let Some(json::Value::Array(violations)) = parsed.get("violations") else {
+            panic!("no violations array");
+        };
+        assert_eq!(violations.len(), 1);
+        assert_eq!(violations[0].get("kind").and_then(json::Value::as_str), Some("unknown_process"));
+        assert_eq!(violations[0].get("line").and_then(json::Value::as_u64), Some(2));
+        assert_eq!(violations[0].get("message").and_then(json::Value::as_str), Some(report.violations[0].message.as_str()));
     }
 }
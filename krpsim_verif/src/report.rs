@@ -0,0 +1,110 @@
+use std::collections::HashMap;
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ViolationKind {
+    UnknownProcess,
+    InsufficientStock,
+    OutOfOrderTime,
+    StockMismatch,
+    FinishMismatch,
+    AfterHorizon,
+    MalformedLine,
+}
+
+impl ViolationKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ViolationKind::UnknownProcess => "unknown_process",
+            ViolationKind::InsufficientStock => "insufficient_stock",
+            ViolationKind::OutOfOrderTime => "out_of_order_time",
+            ViolationKind::StockMismatch => "stock_mismatch",
+            ViolationKind::FinishMismatch => "finish_mismatch",
+            ViolationKind::AfterHorizon => "after_horizon",
+            ViolationKind::MalformedLine => "malformed_line",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Violation {
+    pub line: Option<usize>,
+    pub kind: ViolationKind,
+    pub message: String,
+}
+
+impl Violation {
+    pub fn new(line: Option<usize>, kind: ViolationKind, message: String) -> Self {
+        Violation { line, kind, message }
+    }
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "line {}: [{}] {}", line, self.kind.name(), self.message),
+            None => write!(f, "[{}] {}", self.kind.name(), self.message),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Report {
+    pub violations: Vec<Violation>,
+    pub objective: u64,
+    pub makespan: u64,
+    pub final_stocks: HashMap<String, u64>,
+}
+
+impl Report {
+    pub fn is_valid(&self) -> bool {
+        self.violations.is_empty()
+    }
+
+    pub fn push(&mut self, line: Option<usize>, kind: ViolationKind, message: String) {
+        self.violations.push(Violation::new(line, kind, message));
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut out = String::new();
+        if self.is_valid() {
+            out.push_str("Execution is valid.\n");
+        } else {
+            out.push_str(&format!("Execution is invalid: {} violation(s)\n", self.violations.len()));
+            for violation in &self.violations {
+                out.push_str(&format!("  {}\n", violation));
+            }
+        }
+        out.push_str(&format!("objective: {}\n", self.objective));
+        out.push_str(&format!("makespan: {}\n", self.makespan));
+        out
+    }
+
+    pub fn to_json(&self) -> String {
+        let violations: Vec<String> = self.violations.iter().map(|violation| {
+            let line = violation.line.map_or("null".to_string(), |line| line.to_string());
+            format!(
+                "{{\"line\":{},\"kind\":\"{}\",\"message\":\"{}\"}}",
+                line, violation.kind.name(), escape(&violation.message)
+            )
+        }).collect();
+        format!(
+            "{{\"valid\":{},\"objective\":{},\"makespan\":{},\"violations\":[{}]}}\n",
+            self.is_valid(), self.objective, self.makespan, violations.join(",")
+        )
+    }
+}
+
+fn escape(text: &str) -> String {
+    let mut out = String::new();
+    for c in text.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out
+}
@@ -0,0 +1,84 @@
+use std::collections::HashMap;
+use std::io::{self, Write};
+
+#[derive(Debug, Clone)]
+pub struct Step {
+    pub time: u64,
+    pub stocks: HashMap<String, u64>,
+    pub running: Vec<(String, u64, u64)>,
+}
+
+/// Stock levels and running processes at every cycle where something changed.
+#[derive(Debug, Default)]
+pub struct Trace {
+    pub initial: HashMap<String, u64>,
+    pub steps: Vec<Step>,
+}
+
+impl Trace {
+    pub fn new(initial: &HashMap<String, u64>) -> Self {
+        Trace { initial: initial.clone(), steps: Vec::new() }
+    }
+
+    pub fn record(&mut self, time: u64, stocks: &HashMap<String, u64>, mut running: Vec<(String, u64, u64)>) {
+        running.sort();
+        self.steps.push(Step { time, stocks: stocks.clone(), running });
+    }
+
+    pub fn print(&self, out: &mut impl Write) -> io::Result<()> {
+        let mut previous = &self.initial;
+        for step in &self.steps {
+            let mut names: Vec<&String> = step.stocks.keys().chain(previous.keys()).collect();
+            names.sort();
+            names.dedup();
+
+            let deltas: Vec<String> = names.iter().filter_map(|name| {
+                let before = *previous.get(*name).unwrap_or(&0) as i128;
+                let after = *step.stocks.get(*name).unwrap_or(&0) as i128;
+                match after - before {
+                    0 => None,
+                    delta => Some(format!("{}{:+}", name, delta)),
+                }
+            }).collect();
+
+            let mut grouped: Vec<(&String, u64, u64)> = Vec::new();
+            for (name, end, runs) in &step.running {
+                match grouped.last_mut() {
+                    Some((last, last_end, count)) if *last == name && last_end == end => *count += runs,
+                    _ => grouped.push((name, *end, *runs)),
+                }
+            }
+            let running: Vec<String> = grouped.iter()
+                .map(|(name, end, count)| format!("{} x{} (until {})", name, count, end))
+                .collect();
+
+            writeln!(out, "cycle {}:", step.time)?;
+            writeln!(out, "  deltas: {}", if deltas.is_empty() { "-".to_string() } else { deltas.join(" ") })?;
+            writeln!(out, "  running: {}", if running.is_empty() { "-".to_string() } else { running.join(", ") })?;
+            previous = &step.stocks;
+        }
+        Ok(())
+    }
+
+    /// One row for the initial stocks, then one per step, a step at cycle 0
+    /// included.
+    pub fn write_csv(&self, out: &mut impl Write) -> io::Result<()> {
+        let mut names: Vec<&String> = self.initial.keys()
+            .chain(self.steps.iter().flat_map(|step| step.stocks.keys()))
+            .collect();
+        names.sort();
+        names.dedup();
+
+        let header: Vec<&str> = names.iter().map(|name| name.as_str()).collect();
+        writeln!(out, "time,{}", header.join(","))?;
+
+        let row = |stocks: &HashMap<String, u64>| -> String {
+            names.iter().map(|name| stocks.get(*name).unwrap_or(&0).to_string()).collect::<Vec<_>>().join(",")
+        };
+        writeln!(out, "0,{}", row(&self.initial))?;
+        for step in &self.steps {
+            writeln!(out, "{},{}", step.time, row(&step.stocks))?;
+        }
+        Ok(())
+    }
+}
@@ -1,269 +1,281 @@
-use std::collections::{HashMap, VecDeque};
+use std::collections::HashMap;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
 use crate::Data;
-use crate::Process;
 use crate::delay;
+use crate::progress::Reporter;
+use crate::search::Model;
+use crate::sgs::Timeline;
 use std::time::Instant;
-use std::sync::atomic::{AtomicBool, Ordering as AtomicOrdering};
-use std::sync::Arc;
+use std::sync::atomic::Ordering as AtomicOrdering;
 use rand::prelude::*;
 
-fn initialize_pheromones(processes: &Vec<Process>) -> HashMap<String, f64> {
-    let mut pheromones = HashMap::new();
-    for process in processes {
-        pheromones.insert(process.id.clone(), 1.0);
-    }
-    pheromones
+/* Decisions an ant may take, per process of the file */
+const STEPS_PER_PROCESS: usize = 8;
+/* Ants of an iteration that deposit under the rank-based update */
+const RANKED: usize = 6;
+/* Heuristic of ending the schedule, as a mediocre process */
+const STOP_HEURISTIC: f64 = 0.5;
+/* Iterations without a new best before every trail is reset to the maximum */
+const STAGNATION: u64 = 100;
+
+/// Which ants lay pheromone after an iteration.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Update {
+    /// The iteration best and the best so far
+    Elitist,
+    /// The `RANKED` best ants of the iteration, weighted by rank, and the best so far
+    Rank,
 }
 
-fn construct_solution(
-    data: &Data,
-    pheromones: &HashMap<String, f64>,
-    rng: &mut ThreadRng,
-    timer_flag: &Arc<AtomicBool>,
-) -> (Vec<(String, usize)>, Vec<(String, u64, u64)>) {
-    let mut solution = Vec::new();
-    let mut log = Vec::new();
-    let mut available_stocks = data.stocks.clone();
-    let mut total_time = 0;
-    let mut iteration_count = 0;
-    let max_iterations = 50;
-    let mut last_objective_score = 0;
-    let mut objective_stalled_count = 0;
-
-    loop {
-
-        if timer_flag.load(AtomicOrdering::SeqCst) {
-            break;
-        }
-
-        iteration_count += 1;
-
-        if iteration_count > max_iterations || objective_stalled_count > 5 {
-            break;
-        }
-
-        let next_processes = select_next_processes(&data.processes, &available_stocks, &pheromones, rng);
-        if next_processes.is_empty() {
-            break;
-        }
+/// What a pheromone trail is attached to, besides the process chosen.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Context {
+    /// The index of the decision in the ant's schedule
+    Depth,
+    /// The stock levels, each rounded down to a power of two
+    Stocks,
+}
 
-        let mut max_time_per_step = 0;
-        let mut any_process_executed = false;
-
-        for (process, count) in &next_processes {
-            let process_clone = process.clone();
-            for _ in 0..*count {
-                if update_stocks_and_time(&mut available_stocks, &process_clone) {
-                    solution.push((process.id.clone(), *count));
-                    max_time_per_step = max_time_per_step.max(process.time);
-                    any_process_executed = true;
-                }
-            }
-            /* loging for printing solution */
-            if any_process_executed {
-                log.push((process.id.clone(), *count as u64, total_time));
-            }
-        }
+#[derive(Debug, Clone, Copy)]
+pub struct Settings {
+    pub ants: usize,
+    pub evaporation: f64,
+    pub alpha: f64,
+    pub beta: f64,
+    pub update: Update,
+    pub context: Context,
+}
 
-        if !any_process_executed {
-            break;
-        }
+/// MAX-MIN trails over (context, process) decisions. Evaporation is applied
+/// lazily: a trail stores its level at the iteration it was last touched, and
+/// a trail never touched since the last reset starts from the maximum at that
+/// reset and evaporates from there like any other.
+struct Pheromones {
+    trails: HashMap<(u64, usize), (f64, u64)>,
+    evaporation: f64,
+    max: f64,
+    min: f64,
+    reset: u64,
+}
 
-        total_time += max_time_per_step;
+impl Pheromones {
+    fn new(evaporation: f64, processes: usize) -> Self {
+        /* the best ant deposits at most 1, so trails settle below 1 / evaporation */
+        let max = 1.0 / evaporation;
+        Pheromones { trails: HashMap::new(), evaporation, max, min: max / (2 * processes.max(1)) as f64, reset: 0 }
+    }
 
-        let current_objective_score = evaluate_objective(&data.objectives, &available_stocks);
-        if current_objective_score <= last_objective_score {
-            objective_stalled_count += 1;
-        } else {
-            objective_stalled_count = 0;
-        }
-        last_objective_score = current_objective_score;
+    fn level(&self, key: (u64, usize), iteration: u64) -> f64 {
+        let (level, since) = self.trails.get(&key).copied().unwrap_or((self.max, self.reset));
+        (level * (1.0 - self.evaporation).powi((iteration - since) as i32)).max(self.min)
     }
 
-    (solution, log)
-}
+    fn deposit(&mut self, key: (u64, usize), amount: f64, iteration: u64) {
+        let level = (self.level(key, iteration) + amount).clamp(self.min, self.max);
+        self.trails.insert(key, (level, iteration));
+    }
 
-fn evaluate_objective(objectives: &Vec<String>, stocks: &HashMap<String, u64>) -> u64 {
-    let mut score = 0;
-    for objective in objectives {
-        if let Some(stock) = stocks.get(objective) {
-            score += stock;
-        }
+    fn reinitialize(&mut self, iteration: u64) {
+        self.trails.clear();
+        self.reset = iteration;
     }
-    score
 }
 
-fn select_next_processes<'a>(
-    processes: &'a Vec<Process>,
-    available_stocks: &HashMap<String, u64>,
-    pheromones: &HashMap<String, f64>,
-    rng: &mut ThreadRng
-) -> Vec<(&'a Process, usize)> {
-    let mut selected_processes = Vec::new();
-    let mut process_weights = Vec::new();
-    let mut total_weight = 0.0;
-
-    for process in processes {
-        let mut max_count = u64::MAX;
-
-        for (item, amount) in &process.input {
-            if let Some(stock) = available_stocks.get(item) {
-                max_count = max_count.min(stock / amount);
-            } else {
-                max_count = 0;
-                break;
-            }
-        }
-
-        if max_count > 0 {
-            let pheromone_level = *pheromones.get(&process.id).unwrap();
-            let heuristic_value = 1.0 / (process.time as f64);
-            let weight = pheromone_level * heuristic_value;
+#[derive(Clone)]
+struct Ant {
+    timeline: Timeline,
+    path: Vec<(u64, usize)>,
+    objective: u64,
+}
 
-            process_weights.push((process, max_count as usize, weight));
-            total_weight += weight;
-        }
+impl Ant {
+    fn better_than(&self, other: &Ant) -> bool {
+        self.objective > other.objective
+            || (self.objective == other.objective && self.timeline.makespan < other.timeline.makespan)
     }
+}
 
-    if total_weight > 0.0 {
-        let mut chosen_weight = rng.gen_range(0.0..total_weight);
-        for (process, max_count, weight) in &process_weights {
-            if chosen_weight < *weight {
-                let count = rng.gen_range(1..=*max_count);
-                selected_processes.push((*process, count));
-                break;
-            } else {
-                chosen_weight -= *weight;
+fn context(settings: &Settings, depth: usize, timeline: &Timeline) -> u64 {
+    match settings.context {
+        Context::Depth => depth as u64,
+        Context::Stocks => {
+            let mut hasher = DefaultHasher::new();
+            for &level in timeline.stocks() {
+                (u64::BITS - level.leading_zeros()).hash(&mut hasher);
             }
+            hasher.finish()
         }
     }
+}
 
-    selected_processes
+/* How much a run is worth by the stock values, scaled to [0.1, 1.1] */
+fn heuristic(model: &Model) -> Vec<f64> {
+    let values = model.values();
+    let gains: Vec<f64> = model.processes.iter().map(|step| {
+        let made: f64 = step.output.iter().map(|&(item, qty)| qty as f64 * values[item]).sum();
+        let used: f64 = step.input.iter().map(|&(item, qty)| qty as f64 * values[item]).sum();
+        made - used
+    }).collect();
+    let low = gains.iter().cloned().fold(f64::INFINITY, f64::min);
+    let high = gains.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+    gains.iter().map(|gain| if high > low { (gain - low) / (high - low) + 0.1 } else { 1.0 }).collect()
 }
 
-fn update_stocks_and_time(stocks: &mut HashMap<String, u64>, process: &Process) -> bool {
-    for (input, amount) in &process.input {
-        if let Some(stock) = stocks.get_mut(input) {
-            if *stock < *amount {
-                return false;
-            }
-        } else {
-            return false;
+/// One ant: at each decision, a process that can still start, or stopping
+/// there, is drawn with probability proportional to trail^alpha *
+/// heuristic^beta. A process is placed at its earliest start by the serial
+/// SGS, with every run that fits or a random part of them.
+fn construct(model: &Model, settings: &Settings, pheromones: &Pheromones, eta: &[f64], iteration: u64, rng: &mut impl Rng) -> Ant {
+    let mut timeline = Timeline::new(model);
+    let mut path = Vec::new();
+
+    for depth in 0..model.processes.len() * STEPS_PER_PROCESS {
+        let key = context(settings, depth, &timeline);
+        let choices: Vec<(usize, usize, u64, f64)> = (0..model.processes.len())
+            .filter_map(|process| timeline.earliest(model, process).map(|(k, runs)| (process, k, runs)))
+            .map(|(process, k, runs)| {
+                let weight = pheromones.level((key, process), iteration).powf(settings.alpha) * eta[process].powf(settings.beta);
+                (process, k, runs, weight)
+            })
+            .collect();
+        if choices.is_empty() {
+            break;
         }
-    }
-
-    for (input, amount) in &process.input {
-        if let Some(stock) = stocks.get_mut(input) {
-            *stock -= amount;
+        /* stopping is a decision of its own, with its own trail */
+        let stop = model.processes.len();
+        let stop_weight = pheromones.level((key, stop), iteration).powf(settings.alpha) * STOP_HEURISTIC.powf(settings.beta);
+        let total: f64 = choices.iter().map(|choice| choice.3).sum::<f64>() + stop_weight;
+        if rng.gen::<f64>() * total < stop_weight {
+            path.push((key, stop));
+            break;
         }
-    }
+        let Ok(&(process, k, runs, _)) = choices.choose_weighted(rng, |choice| choice.3) else {
+            break;
+        };
 
-    for (output, amount) in &process.output {
-        let stock = stocks.entry(output.clone()).or_insert(0);
-        *stock += amount;
+        let count = if rng.gen_bool(0.5) { runs } else { rng.gen_range(1..=runs) };
+        timeline.place(model, process, count, k);
+        path.push((key, process));
     }
 
-    true
+    let objective = model.objective(timeline.stocks());
+    Ant { timeline, path, objective }
 }
 
-fn evaluate_solution(data: &Data, solution: &Vec<(String, usize)>) -> (u64, u64, HashMap<String, u64>) {
-    let mut available_stocks = data.stocks.clone();
-    let mut total_time = 0;
-    let mut max_time_per_step = 0;
-
-    for (process_id, count) in solution {
-        if let Some(process) = data.processes.iter().find(|p| &p.id == process_id) {
-            for _ in 0..*count {
-                if update_stocks_and_time(&mut available_stocks, process) {
-                    max_time_per_step = max_time_per_step.max(process.time);
-                }
-            }
-        }
-        total_time += max_time_per_step;
-        max_time_per_step = 0;
-    }
-
-    let mut objective_score = 0;
-    for objective in &data.objectives {
-        if let Some(stock) = available_stocks.get(objective) {
-            objective_score += stock;
-        }
-    }
-
-    (objective_score, total_time, available_stocks)
+/* Construction dominates an iteration, so the ants are split over every core */
+fn construct_all(model: &Model, settings: &Settings, pheromones: &Pheromones, eta: &[f64], iteration: u64) -> Vec<Ant> {
+    let threads = std::thread::available_parallelism().map_or(1, |n| n.get()).min(settings.ants).max(1);
+    std::thread::scope(|scope| {
+        let workers: Vec<_> = (0..threads)
+            .map(|worker| {
+                let share = settings.ants / threads + usize::from(worker < settings.ants % threads);
+                scope.spawn(move || {
+                    let mut rng = thread_rng();
+                    (0..share).map(|_| construct(model, settings, pheromones, eta, iteration, &mut rng)).collect::<Vec<_>>()
+                })
+            })
+            .collect();
+        workers.into_iter().flat_map(|worker| worker.join().unwrap()).collect()
+    })
 }
 
-fn update_pheromones(
-    pheromones: &mut HashMap<String, f64>,
-    solutions: &Vec<(Vec<(String, usize)>, u64, u64)>
-) {
-    for pheromone in pheromones.values_mut() {
-        *pheromone *= 0.9;
-    }
-
-    for (solution, objective_score, total_time) in solutions {
-        let pheromone_increase = *objective_score as f64 / *total_time as f64;
-        for (process_id, count) in solution {
-            if let Some(pheromone) = pheromones.get_mut(process_id) {
-                *pheromone += pheromone_increase * *count as f64;
-            }
-        }
-    }
+/* Relative to the best objective so far, so the best ant lays 1 */
+fn quality(objective: u64, best_objective: u64) -> f64 {
+    (objective + 1) as f64 / (best_objective + 1) as f64
 }
 
-pub fn aco_optimization(
-    data: &Data, 
-    num_iterations: usize, 
-    num_ants: usize, 
-    delay: u32
-) -> (Vec<(String, usize)>, u64, HashMap<String, u64>, Vec<(String, u64, u64)>) {
-    let mut pheromones = initialize_pheromones(&data.processes);
-    let mut rng = thread_rng();
-
-    let mut best_solution = Vec::new();
-    let mut best_score = 0;
-    let mut best_time = u64::MAX;
-    let mut best_stocks = HashMap::new();
-    let mut best_log = Vec::new();
-
-    let timer_flag = Arc::new(AtomicBool::new(false));
-    let timer_flag_clone = Arc::clone(&timer_flag);
-
-    std::thread::spawn(move || {
-        std::thread::sleep(std::time::Duration::from_secs(delay as u64));
-        timer_flag_clone.store(true, AtomicOrdering::SeqCst);
-    });
+/// MAX-MIN Ant System over SGS schedules. Every iteration, `settings.ants`
+/// ants are built in parallel from the trails, which then evaporate and get
+/// deposits from the best ants only, bounded between a minimum and a maximum so
+/// no decision is ever ruled out. Trails are reset when the best stalls.
+pub fn aco_optimization(data: &Data, num_iterations: usize, delay: u32, settings: &Settings, progress: &Reporter) -> Option<(u64, HashMap<String, u64>, Vec<(String, u64, u64)>)> {
+    let model = Model::new(data);
+    if model.processes.is_empty() {
+        return None;
+    }
+    let settings = Settings { ants: settings.ants.max(1), evaporation: settings.evaporation.clamp(1e-3, 1.0), ..*settings };
+    let eta = heuristic(&model);
+    let mut pheromones = Pheromones::new(settings.evaporation, model.processes.len());
 
+    let timer_flag = delay::start_timer(std::time::Duration::from_secs(delay as u64));
     let start = Instant::now();
 
-    for _ in 0..num_iterations {
+    /* doing nothing is a schedule too, and the one to beat */
+    let idle = Timeline::new(&model);
+    let mut best = Ant { objective: model.objective(idle.stocks()), timeline: idle, path: Vec::new() };
+    let mut last_best: u64 = 0;
+    let mut iterations: u64 = 0;
+
+    while (iterations as usize) < num_iterations {
         if timer_flag.load(AtomicOrdering::SeqCst) {
-            println!("Timer elapsed, stopping optimization\n");
+            say!("Timer elapsed, stopping optimization");
             break;
         }
-        
-        let mut solutions = Vec::new();
-
-        for _ in 0..num_ants {
-            let (solution, log) = construct_solution(data, &pheromones, &mut rng, &timer_flag);
-            let (objective_score, total_time, final_stocks) = evaluate_solution(data, &solution);
-            solutions.push((solution.clone(), objective_score, total_time));
-
-            if objective_score > best_score || (objective_score == best_score && total_time < best_time) {
-                best_score = objective_score;
-                best_time = total_time;
-                best_solution = solution;
-                best_stocks = final_stocks;
-                best_log = log;
+        iterations += 1;
+
+        let mut ants = construct_all(&model, &settings, &pheromones, &eta, iterations);
+        ants.sort_by(|a, b| b.objective.cmp(&a.objective).then(a.timeline.makespan.cmp(&b.timeline.makespan)));
+        if ants[0].better_than(&best) {
+            best = ants[0].clone();
+            last_best = iterations;
+        }
+        let champion = &best;
+
+        let depositing: Vec<(&Ant, f64)> = match settings.update {
+            Update::Elitist => ants.iter().take(1).map(|ant| (ant, 1.0)).collect(),
+            Update::Rank => ants.iter().take(RANKED).enumerate()
+                .map(|(rank, ant)| (ant, (RANKED - rank) as f64 / RANKED as f64))
+                .collect(),
+        };
+        for (ant, weight) in depositing.into_iter().chain(std::iter::once((champion, 1.0))) {
+            let amount = weight * quality(ant.objective, champion.objective);
+            for &key in &ant.path {
+                pheromones.deposit(key, amount, iterations);
             }
         }
 
-        update_pheromones(&mut pheromones, &solutions);
+        if iterations - last_best > STAGNATION {
+            pheromones.reinitialize(iterations);
+            last_best = iterations;
+        }
+
+        progress.tick(iterations, champion.objective, champion.timeline.makespan, || format!("{} ants, {} trails", settings.ants, pheromones.trails.len()));
     }
 
+    progress.finish(iterations, best.objective, best.timeline.makespan, format!("{} iterations", iterations));
+
     let elapsed = start.elapsed();
+    say!("Ant Colony Optimization executed in: {}.{:03} seconds\n", elapsed.as_secs(), elapsed.subsec_millis());
 
-    println!("Ant Colony Optimization executed in: {}.{:03} seconds\n", elapsed.as_secs(), elapsed.subsec_millis());
+    Some((best.timeline.makespan, model.stocks_map(best.timeline.stocks()), best.timeline.log(&model)))
+}
 
-    (best_solution, best_time, best_stocks, best_log)
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn untouched_trails_evaporate() {
+        let mut pheromones = Pheromones::new(0.1, 4);
+        assert_eq!(pheromones.level((0, 0), 0), pheromones.max);
+        assert!(pheromones.level((0, 0), 10) < pheromones.max * 0.35);
+        assert_eq!(pheromones.level((0, 0), 100), pheromones.min);
+
+        /* a reset brings every trail back to the maximum from then on */
+        pheromones.deposit((0, 1), 1.0, 100);
+        pheromones.reinitialize(100);
+        assert_eq!(pheromones.level((0, 0), 100), pheromones.max);
+        assert_eq!(pheromones.level((0, 1), 100), pheromones.max);
+        assert_eq!(pheromones.level((0, 0), 200), pheromones.min);
+    }
+
+    #[test]
+    fn reinforced_trails_stay_above_untouched_ones() {
+        let mut pheromones = Pheromones::new(0.1, 4);
+        for iteration in 1..=50 {
+            pheromones.deposit((0, 1), 1.0, iteration);
+        }
+        assert!(pheromones.level((0, 1), 50) > pheromones.level((0, 0), 50));
+    }
 }
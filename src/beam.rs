@@ -0,0 +1,152 @@
+use crate::Data;
+use crate::delay;
+use crate::progress::Reporter;
+use crate::search::{Model, Node};
+use std::collections::{HashMap, HashSet};
+use std::time::Instant;
+use std::sync::atomic::Ordering as AtomicOrdering;
+
+/* Successors evaluated per state, as a multiple of the beam width */
+const BRANCHING: usize = 4;
+
+/// How the beam ranks the states of a layer.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Evaluation {
+    /// Settled stocks weighted by what they can still turn into
+    StockScores,
+    /// Settled objective only
+    Objective,
+    /// LP upper bound of the settled stocks
+    Bound,
+}
+
+impl Evaluation {
+    /* Ties, and unbounded relaxations, are broken by the stock value */
+    fn score(&self, model: &Model, weights: &[f64], node: &Node) -> (f64, f64) {
+        let settled = node.settled(model);
+        let value = settled.iter().zip(weights).map(|(&qty, weight)| qty as f64 * weight).sum();
+        let primary = match self {
+            Evaluation::StockScores => value,
+            Evaluation::Objective => model.objective(&settled) as f64,
+            Evaluation::Bound => match model.upper_bound(&settled) {
+                u64::MAX => f64::INFINITY,
+                bound => bound as f64,
+            },
+        };
+        (primary, value)
+    }
+}
+
+/// Keeps the `width` best candidates, skipping states already kept. A
+/// quarter of the beam goes to the best settled objectives whatever the
+/// evaluation, so states that already pay are not lost to ones that only look
+/// promising. The rest is ranked by the evaluation, taking at most a quarter
+/// of the beam from the same parent on the first pass, so a single promising
+/// state cannot fill the whole layer with near copies.
+fn select(candidates: Vec<((f64, f64), u64, usize, Node)>, width: usize) -> Vec<Node> {
+    let mut candidates = candidates;
+    let quarter = (width / 4).max(1);
+    let mut seen = HashSet::new();
+    let mut beam = Vec::with_capacity(width);
+
+    candidates.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.3.time.cmp(&b.3.time)));
+    let mut rest = Vec::with_capacity(candidates.len());
+    for candidate in candidates {
+        if beam.len() < quarter && seen.insert(candidate.3.key()) {
+            beam.push(candidate.3);
+        } else {
+            rest.push(candidate);
+        }
+    }
+
+    rest.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(std::cmp::Ordering::Equal)
+        .then_with(|| a.3.time.cmp(&b.3.time)));
+    let mut taken: HashMap<usize, usize> = HashMap::new();
+    let mut skipped = Vec::new();
+    for (_, _, parent, node) in rest {
+        if beam.len() >= width {
+            break;
+        }
+        if !seen.insert(node.key()) {
+            continue;
+        }
+        let count = taken.entry(parent).or_insert(0);
+        if *count < quarter {
+            *count += 1;
+            beam.push(node);
+        } else {
+            skipped.push(node);
+        }
+    }
+    for node in skipped {
+        if beam.len() >= width {
+            break;
+        }
+        beam.push(node);
+    }
+    beam
+}
+
+/// Layered beam search over the concurrent schedule: every state of the beam
+/// is expanded to the next completion through a random sample of its starting
+/// sets, drawn without enumerating them all, and the `width` best successors
+/// for `evaluation` form the next layer. Any state reached may become the
+/// result, best objective first, then shortest makespan.
+pub fn optimize(data: Data, delay: u32, width: usize, evaluation: Evaluation, progress: &Reporter) -> Option<(u64, HashMap<String, u64>, Vec<(String, u64, u64)>)> {
+    let model = Model::new(&data);
+    let weights = model.values();
+    /* whether the relaxation is unbounded does not depend on the stocks (the
+     * empty plan is always feasible), so when it is at the root every state
+     * would tie at infinity and be ranked by its stock value anyway */
+    let evaluation = match evaluation {
+        Evaluation::Bound if model.upper_bound(&model.initial) == u64::MAX => Evaluation::StockScores,
+        evaluation => evaluation,
+    };
+    let timer_flag = delay::start_timer(std::time::Duration::from_secs(delay as u64));
+    let start = Instant::now();
+    let mut rng = rand::thread_rng();
+
+    let root = Node::root(&model);
+    let mut best_objective = root.objective(&model);
+    let mut best_makespan = 0;
+    let mut best_node = root.clone();
+
+    let mut beam = vec![root];
+    let mut layer: u64 = 0;
+    let mut expanded: u64 = 0;
+
+    while !beam.is_empty() {
+        if timer_flag.load(AtomicOrdering::SeqCst) {
+            say!("Timer elapsed, stopping optimization");
+            break;
+        }
+
+        let mut candidates = Vec::new();
+        for (parent, node) in beam.iter().enumerate() {
+            let objective = node.objective(&model);
+            if objective > best_objective || (objective == best_objective && node.makespan < best_makespan) {
+                best_objective = objective;
+                best_makespan = node.makespan;
+                best_node = node.clone();
+            }
+
+            expanded += 1;
+            for combination in node.sample_combinations(&model, width * BRANCHING, &mut rng) {
+                if let Some(child) = node.child(&model, &combination) {
+                    candidates.push((evaluation.score(&model, &weights, &child), child.objective(&model), parent, child));
+                }
+            }
+        }
+
+        layer += 1;
+        beam = select(candidates, width);
+        progress.tick(expanded, best_objective, best_makespan, || format!("layer {} beam {}", layer, beam.len()));
+    }
+
+    progress.finish(expanded, best_objective, best_makespan, format!("{} layers", layer));
+
+    let elapsed = start.elapsed();
+    say!("Beam search executed in: {}.{:03} seconds\n", elapsed.as_secs(), elapsed.subsec_millis());
+
+    Some((best_node.makespan, model.stocks_map(&best_node.settled(&model)), best_node.log))
+}
@@ -0,0 +1,117 @@
+use crate::Data;
+use crate::delay;
+use crate::progress::Reporter;
+use crate::search::{Model, Node, MAX_VISITED};
+use std::collections::HashMap;
+use std::time::Instant;
+use std::sync::atomic::Ordering as AtomicOrdering;
+
+/// Depth-first branch and bound over the concurrent schedule. Nodes are pruned
+/// when the LP bound of their settled stocks cannot beat the incumbent, or when
+/// the same state was already reached earlier. Returns whether the result is
+/// proven optimal, i.e. the search ended before the timer without cutting any
+/// enumeration of starts short.
+pub fn optimize(data: Data, delay: u32, progress: &Reporter) -> Option<(u64, HashMap<String, u64>, Vec<(String, u64, u64)>, bool)> {
+    let model = Model::new(&data);
+    let timer_flag = delay::start_timer(std::time::Duration::from_secs(delay as u64));
+    let start = Instant::now();
+
+    let root = Node::root(&model);
+    let mut best_objective = root.objective(&model);
+    let mut best_makespan = 0;
+    let mut best_log = Vec::new();
+    let mut best_stocks = root.settled(&model);
+
+    let mut visited: HashMap<(Vec<u64>, Vec<(u64, usize, u64)>), u64> = HashMap::new();
+    let mut stack = vec![(model.upper_bound(&root.stocks), root)];
+    let mut expanded: u64 = 0;
+    let mut pruned: u64 = 0;
+    let mut exhaustive = true;
+
+    while let Some((bound, node)) = stack.pop() {
+        if timer_flag.load(AtomicOrdering::SeqCst) {
+            say!("Timer elapsed, stopping optimization");
+            break;
+        }
+
+        /* the incumbent may have improved since this node was pushed */
+        if bound < best_objective || (bound == best_objective && node.makespan >= best_makespan) {
+            pruned += 1;
+            continue;
+        }
+
+        let key = node.key();
+        match visited.get(&key) {
+            Some(&time) if time <= node.time => {
+                pruned += 1;
+                continue;
+            }
+            _ if visited.len() < MAX_VISITED => {
+                visited.insert(key, node.time);
+            }
+            _ => {}
+        }
+
+        let objective = node.objective(&model);
+        if objective > best_objective || (objective == best_objective && node.makespan < best_makespan) {
+            best_objective = objective;
+            best_makespan = node.makespan;
+            best_log = node.log.clone();
+            best_stocks = node.settled(&model);
+        }
+
+        expanded += 1;
+        let (successors, complete) = node.successors(&model, &timer_flag);
+        exhaustive &= complete;
+        let mut children: Vec<(u64, Node)> = successors.into_iter().rev()
+            .map(|child| (model.upper_bound(&child.settled(&model)), child))
+            .filter(|(bound, child)| {
+                *bound > best_objective || (*bound == best_objective && child.makespan < best_makespan)
+            })
+            .collect();
+        children.sort_by_key(|(bound, child)| (*bound, std::cmp::Reverse(child.makespan)));
+        stack.extend(children);
+
+        progress.tick(expanded, best_objective, best_makespan, || format!("stack {} pruned {}", stack.len(), pruned));
+    }
+
+    let proven = exhaustive && !timer_flag.load(AtomicOrdering::SeqCst);
+    progress.finish(expanded, best_objective, best_makespan, (if proven { "proven optimal" } else { "not proven" }).to_string());
+
+    let elapsed = start.elapsed();
+
+    say!("Branch and bound executed in: {}.{:03} seconds\n", elapsed.as_secs(), elapsed.subsec_millis());
+
+    Some((best_makespan, model.stocks_map(&best_stocks), best_log, proven))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulator;
+
+    fn assert_proven(data: Data, expected: u64) {
+        let (makespan, stocks, log, proven) = optimize(data.clone(), 60, &Reporter::disabled()).unwrap();
+        assert!(proven);
+        assert_eq!(simulator::objective(&data.objectives, &stocks), expected);
+        let outcome = simulator::simulate(&data, &log).unwrap();
+        assert_eq!((outcome.objective, outcome.makespan), (expected, makespan));
+    }
+
+    #[test]
+    fn simple_is_proven() {
+        assert_proven(crate::load_data("resources/simple").unwrap(), 13);
+    }
+
+    #[test]
+    fn ikea_is_proven() {
+        assert_proven(crate::load_data("resources/ikea").unwrap(), 1);
+    }
+
+    #[test]
+    fn steak_is_proven_under_a_horizon() {
+        let mut data = crate::load_data("resources/steak").unwrap();
+        data.horizon = Some(30);
+        assert_proven(data, 3);
+    }
+}
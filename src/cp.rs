@@ -0,0 +1,267 @@
+use crate::Data;
+use crate::delay;
+use crate::progress::Reporter;
+use crate::search::{Model, Node, MAX_VISITED};
+use std::collections::HashMap;
+use std::time::Instant;
+use std::sync::atomic::Ordering as AtomicOrdering;
+
+/// What propagation leaves of a node: the processes that can still complete
+/// by the horizon, and bounds on every stock from now to the horizon.
+struct Domains {
+    alive: Vec<bool>,
+    /// Stock once the running processes are done, which nothing can lower
+    /// without starting something else
+    lower: Vec<u64>,
+    /// `u64::MAX` when some live process produces the stock
+    upper: Vec<u64>,
+}
+
+/// Forward propagation to a fixpoint. A stock is available at once if it is
+/// there, when a running batch delivers it, or after its earliest live
+/// producer; a process can start once all its inputs are available. Processes
+/// that cannot end by the horizon, or need more of an input than its upper
+/// bound, are dead, which may in turn kill their consumers.
+fn propagate(model: &Model, node: &Node, horizon: u64) -> Domains {
+    let lower = node.settled(model);
+    let items = model.names.len();
+    let mut alive = vec![true; model.processes.len()];
+
+    loop {
+        let mut upper = lower.clone();
+        let mut available: Vec<u64> = (0..items).map(|item| if node.stocks[item] > 0 { node.time } else { u64::MAX }).collect();
+        for &(end, p, _) in &node.running {
+            for &(item, _) in &model.processes[p].output {
+                available[item] = available[item].min(end);
+            }
+        }
+
+        /* earliest availability, relaxed like shortest paths: one round per process at most */
+        for _ in 0..=model.processes.len() {
+            let mut changed = false;
+            for (_, step) in model.processes.iter().enumerate().filter(|&(p, _)| alive[p]) {
+                let start = step.input.iter().map(|&(item, _)| available[item]).max().unwrap_or(node.time).max(node.time);
+                if start == u64::MAX || start + step.time > horizon {
+                    continue;
+                }
+                for &(item, _) in &step.output {
+                    upper[item] = u64::MAX;
+                    if start + step.time < available[item] {
+                        available[item] = start + step.time;
+                        changed = true;
+                    }
+                }
+            }
+            if !changed {
+                break;
+            }
+        }
+
+        let dead: Vec<usize> = model.processes.iter().enumerate()
+            .filter(|&(p, step)| {
+                let start = step.input.iter().map(|&(item, _)| available[item]).max().unwrap_or(node.time).max(node.time);
+                let late = start == u64::MAX || start + step.time > horizon;
+                let short = step.input.iter().any(|&(item, qty)| upper[item] < qty);
+                alive[p] && (late || short)
+            })
+            .map(|(p, _)| p)
+            .collect();
+        if dead.is_empty() {
+            return Domains { alive, lower, upper };
+        }
+        for p in dead {
+            alive[p] = false;
+        }
+    }
+}
+
+/// Depth-first constraint search over the starts of each decision point (the
+/// start and every completion) up to `horizon`. Each node propagates stock
+/// bounds and earliest starts, only branches on the processes still alive, and
+/// is pruned when the LP bound over those processes cannot beat the
+/// incumbent. Runs never end after the horizon, so the objective is the one
+/// reached by then. Returns whether the result is proven optimal for it.
+pub fn optimize(data: Data, delay: u32, horizon: u64, progress: &Reporter) -> Option<(u64, HashMap<String, u64>, Vec<(String, u64, u64)>, bool)> {
+    let mut model = Model::new(&data);
+    model.horizon = model.horizon.min(horizon);
+    let timer_flag = delay::start_timer(std::time::Duration::from_secs(delay as u64));
+    let start = Instant::now();
+
+    let root = Node::root(&model);
+    let mut best_objective = root.objective(&model);
+    let mut best_makespan = 0;
+    let mut best_log = Vec::new();
+    let mut best_stocks = root.settled(&model);
+
+    let mut visited: HashMap<(Vec<u64>, Vec<(u64, usize, u64)>), u64> = HashMap::new();
+    let mut stack = vec![root];
+    let mut expanded: u64 = 0;
+    let mut pruned: u64 = 0;
+    let mut exhaustive = true;
+
+    while let Some(node) = stack.pop() {
+        if timer_flag.load(AtomicOrdering::SeqCst) {
+            say!("Timer elapsed, stopping optimization");
+            break;
+        }
+
+        let key = node.key();
+        match visited.get(&key) {
+            Some(&time) if time <= node.time => {
+                pruned += 1;
+                continue;
+            }
+            _ if visited.len() < MAX_VISITED => {
+                visited.insert(key, node.time);
+            }
+            _ => {}
+        }
+
+        let objective = model.objective(&node.settled(&model));
+        if objective > best_objective || (objective == best_objective && node.makespan < best_makespan) {
+            best_objective = objective;
+            best_makespan = node.makespan;
+            best_log = node.log.clone();
+            best_stocks = node.settled(&model);
+        }
+
+        let domains = propagate(&model, &node, horizon);
+        let bound = match model.upper_bound_with(&domains.lower, &domains.alive) {
+            u64::MAX => u64::MAX,
+            bound => bound.min(model.objectives.iter().map(|&item| domains.upper[item]).fold(0u64, |sum, upper| sum.saturating_add(upper))),
+        };
+        if bound < best_objective || (bound == best_objective && node.makespan >= best_makespan) {
+            pruned += 1;
+            continue;
+        }
+
+        expanded += 1;
+        let (combinations, complete) = node.combinations(&model, &timer_flag);
+        exhaustive &= complete;
+        /* the greedy starts end up on top of the stack */
+        for combination in combinations.into_iter().rev() {
+            if combination.iter().any(|&(process, _)| !domains.alive[process]) {
+                continue;
+            }
+            if let Some(child) = node.child(&model, &combination) {
+                stack.push(child);
+            }
+        }
+
+        progress.tick(expanded, best_objective, best_makespan, || format!("stack {} pruned {}", stack.len(), pruned));
+    }
+
+    let proven = exhaustive && !timer_flag.load(AtomicOrdering::SeqCst);
+    progress.finish(expanded, best_objective, best_makespan, (if proven { "proven optimal" } else { "not proven" }).to_string());
+
+    let elapsed = start.elapsed();
+    say!("Constraint search executed in: {}.{:03} seconds\n", elapsed.as_secs(), elapsed.subsec_millis());
+
+    Some((best_makespan, model.stocks_map(&best_stocks), best_log, proven))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulator;
+    use std::collections::{BTreeMap, HashSet};
+
+    type Stocks = BTreeMap<String, u64>;
+    /* (end, process, runs), sorted */
+    type Running = Vec<(u64, usize, u64)>;
+
+    /* Every way of starting runs of `processes[index..]` at `time` that fit in
+     * the stocks and end by the horizon */
+    fn starts(data: &Data, index: usize, time: u64, horizon: u64, mut stocks: Stocks, running: Running, out: &mut Vec<(Stocks, Running)>) {
+        let Some(process) = data.processes.get(index) else {
+            out.push((stocks, running));
+            return;
+        };
+        starts(data, index + 1, time, horizon, stocks.clone(), running.clone(), out);
+        if time + process.time > horizon {
+            return;
+        }
+        for runs in 1.. {
+            if process.input.iter().any(|(item, qty)| stocks.get(item).copied().unwrap_or(0) < *qty) {
+                break;
+            }
+            for (item, qty) in &process.input {
+                *stocks.get_mut(item).unwrap() -= qty;
+            }
+            let mut started = running.clone();
+            started.push((time + process.time, index, runs));
+            started.sort();
+            starts(data, index + 1, time, horizon, stocks.clone(), started, out);
+        }
+    }
+
+    /* Best objective over every set of starts at cycle 0 and at every
+     * completion, replayed here on its own rather than through the search
+     * nodes. Stocks only grow at completions, so a run started in between
+     * could have started at the previous one. */
+    fn brute_force(data: &Data, horizon: u64) -> u64 {
+        assert!(data.processes.iter().all(|process| !process.input.is_empty()));
+        let initial: Stocks = data.stocks.iter().map(|(item, qty)| (item.clone(), *qty)).collect();
+        let mut visited = HashSet::new();
+        let mut stack = vec![(0, initial, Vec::new())];
+        let mut best = 0;
+        while let Some((time, stocks, running)) = stack.pop() {
+            if !visited.insert((time, stocks.clone(), running.clone())) {
+                continue;
+            }
+            let mut out = Vec::new();
+            starts(data, 0, time, horizon, stocks, running, &mut out);
+            for (mut stocks, running) in out {
+                let Some(&(next, _, _)) = running.first() else {
+                    best = best.max(simulator::objective(&data.objectives, &stocks.into_iter().collect()));
+                    continue;
+                };
+                let mut pending = Vec::new();
+                for (end, index, runs) in running {
+                    if end == next {
+                        for (item, qty) in &data.processes[index].output {
+                            *stocks.entry(item.clone()).or_insert(0) += qty * runs;
+                        }
+                    } else {
+                        pending.push((end, index, runs));
+                    }
+                }
+                stack.push((next, stocks, pending));
+            }
+        }
+        best
+    }
+
+    fn assert_optimal(data: Data, horizon: u64) {
+        let expected = brute_force(&data, horizon);
+        let (_, stocks, _, proven) = optimize(data.clone(), 30, horizon, &Reporter::disabled()).unwrap();
+        assert!(proven, "not proven at horizon {}", horizon);
+        assert_eq!(simulator::objective(&data.objectives, &stocks), expected, "horizon {}", horizon);
+    }
+
+    #[test]
+    fn resources_match_brute_force() {
+        for (file, horizons) in [("resources/simple", [30, 60, 65]), ("resources/steak", [10, 20, 30]), ("resources/ikea", [20, 50, 60])] {
+            let data = crate::load_data(file).unwrap();
+            for horizon in horizons {
+                assert_optimal(data.clone(), horizon);
+            }
+        }
+    }
+
+    #[test]
+    fn chain_matches_brute_force() {
+        let data = crate::inline_data("a:4\nsplit:(a:1):(b:2):3\njoin:(b:3):(c:1):4\nslow:(a:2):(c:1):9\noptimize:(c)\n");
+        for horizon in [0, 4, 7, 9, 12, 20] {
+            assert_optimal(data.clone(), horizon);
+        }
+    }
+
+    #[test]
+    fn shared_tool_matches_brute_force() {
+        let data = crate::inline_data("tool:1\nraw:3\nquick:(raw:1;tool:1):(done:1;tool:1):2\nbatch:(raw:2;tool:1):(done:3;tool:1):5\noptimize:(done)\n");
+        for horizon in [1, 2, 5, 6, 7, 10] {
+            assert_optimal(data.clone(), horizon);
+        }
+    }
+}
@@ -1,6 +1,7 @@
 use crate::Data;
 use crate::Process;
 use crate::delay;
+use crate::progress::Reporter;
 use std::collections::{HashMap, BinaryHeap, HashSet};
 use std::hash::{Hash, Hasher};
 use std::cmp::Ordering;
@@ -51,7 +52,7 @@ impl State {
         State { time, stocks: StockState(stocks), objectives: objectives_map, log }
     }
 
-    fn apply_processes(&self, processes: &[Process], objectives: &[String], timer_flag: &Arc<AtomicBool>) -> Vec<Self> {
+    fn apply_processes(&self, processes: &[Process], objectives: &[String], horizon: Option<u64>, timer_flag: &Arc<AtomicBool>) -> Vec<Self> {
         let mut new_states = Vec::new();
         let mut process_combinations = vec![];
 
@@ -84,8 +85,13 @@ impl State {
                 }
 
                 max_time = max_time.max(process.time);
+                /* every run of the combination starts now */
+                if horizon.is_some_and(|horizon| self.time + process.time > horizon) {
+                    valid_combination = false;
+                    break;
+                }
 
-                new_log.push((process.id.clone(), times, self.time + max_time));
+                new_log.push((process.id.clone(), times, self.time));
             }
 
             if valid_combination {
@@ -148,7 +154,7 @@ impl State {
     }
 }
 
-pub fn optimize(data: Data, delay: u32) -> Option<(u64, HashMap<String, u64>, Vec<(String, u64, u64)>)> {
+pub fn optimize(data: Data, delay: u32, progress: &Reporter) -> Option<(u64, HashMap<String, u64>, Vec<(String, u64, u64)>)> {
     let mut heap = BinaryHeap::new();
     let mut visited = HashSet::new();
     let mut best_time = u64::MAX;
@@ -165,7 +171,7 @@ pub fn optimize(data: Data, delay: u32) -> Option<(u64, HashMap<String, u64>, Ve
 
         /* delay checker */
         if timer_flag.load(AtomicOrdering::SeqCst) {
-            println!("Timer elapsed, stopping optimization");
+            say!("Timer elapsed, stopping optimization");
             break;
         }
 
@@ -188,15 +194,19 @@ pub fn optimize(data: Data, delay: u32) -> Option<(u64, HashMap<String, u64>, Ve
             best_log = Some(state.log.clone());
         }
 
-        let new_states = state.apply_processes(&data.processes, &data.objectives, &timer_flag);
+        let new_states = state.apply_processes(&data.processes, &data.objectives, data.horizon, &timer_flag);
         for new_state in new_states {
             heap.push(new_state);
         }
+
+        progress.tick(visited.len() as u64, best_objective_sum, best_time, || format!("open set {}", heap.len()));
     }
 
+    progress.finish(visited.len() as u64, best_objective_sum, best_time, format!("open set {}", heap.len()));
+
     let elapsed = start.elapsed();
 
-    println!("Dijkstra executed in: {}.{:03} seconds\n", elapsed.as_secs(), elapsed.subsec_millis());
+    say!("Dijkstra executed in: {}.{:03} seconds\n", elapsed.as_secs(), elapsed.subsec_millis());
 
     best_stocks.map(|stocks| (best_time, stocks, best_log.unwrap_or_default()))
 }
@@ -1,8 +1,11 @@
 use std::fs::File;
-use std::io::{self, Write};
+use std::io::{self, BufWriter, Write};
 use std::collections::HashMap;
 use std::thread::{self, JoinHandle};
 
+/* Larger batches are written as one `time:process:count` line */
+const MAX_EXPANDED: u64 = 10_000;
+
 pub fn run_in_thread(
     filename: String,
     final_stocks: HashMap<String, u64>,
@@ -10,7 +13,12 @@ pub fn run_in_thread(
     finish_time: u64,
 ) -> JoinHandle<()> {
     thread::spawn(move || {
-        if let Err(e) = generate_log_file(filename, final_stocks, log, finish_time) {
+        let result = if filename.ends_with(".json") {
+            generate_json_file(filename, final_stocks, log, finish_time)
+        } else {
+            generate_log_file(filename, final_stocks, log, finish_time)
+        };
+        if let Err(e) = result {
             eprintln!("Failed to generate log file: {}", e);
         }
     })
@@ -22,9 +30,13 @@ pub fn generate_log_file(
     log: Vec<(String, u64, u64)>,
     finish_time: u64,
 ) -> io::Result<()> {
-    let mut file = File::create(filename)?;
+    let mut file = BufWriter::new(File::create(filename)?);
 
     for (process, count, time) in &log {
+        if *count > MAX_EXPANDED {
+            writeln!(file, "{}:{}:{}", time, process, count)?;
+            continue;
+        }
         for _ in 0..*count {
             writeln!(file, "{}:{}", time, process)?;
         }
@@ -40,4 +52,32 @@ pub fn generate_log_file(
     }
 
     Ok(())
-}
\ No newline at end of file
+}
+
+pub fn generate_json_file(
+    filename: String,
+    final_stocks: HashMap<String, u64>,
+    log: Vec<(String, u64, u64)>,
+    finish_time: u64,
+) -> io::Result<()> {
+    let mut file = BufWriter::new(File::create(filename)?);
+
+    let schedule: Vec<String> = log.iter()
+        .map(|(process, count, time)| format!("    {{\"time\":{},\"process\":\"{}\",\"count\":{}}}", time, escape(process), count))
+        .collect();
+    let stocks: Vec<String> = final_stocks.iter()
+        .map(|(stock, amount)| format!("\"{}\":{}", escape(stock), amount))
+        .collect();
+
+    writeln!(file, "{{")?;
+    writeln!(file, "  \"schedule\":[\n{}\n  ],", schedule.join(",\n"))?;
+    writeln!(file, "  \"finished_at\":{},", finish_time)?;
+    writeln!(file, "  \"final_stocks\":{{{}}}", stocks.join(","))?;
+    writeln!(file, "}}")?;
+
+    Ok(())
+}
+
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
@@ -1,152 +1,207 @@
 extern crate rand;
-use crate::stock_scores;
 use crate::Data;
-use crate::Process;
 use crate::delay;
+use crate::sgs::{self, Timeline};
+use crate::progress::Reporter;
+use crate::search::Model;
 use rand::Rng;
+use rand::seq::SliceRandom;
+use std::cmp::Reverse;
 use std::collections::HashMap;
-use crate::stock_scores::precompute_stock_scores;
-use std::cmp::Ordering;
 use std::time::Instant;
-use std::sync::atomic::{AtomicBool, Ordering as AtomicOrdering};
-use std::sync::Arc;
+use std::sync::atomic::Ordering as AtomicOrdering;
+
+/* Chromosome length, per process of the file */
+const GENES_PER_PROCESS: usize = 8;
+const TOURNAMENT_SIZE: usize = 3;
+const ELITES: usize = 2;
+const MUTATION_RATE: f64 = 0.1;
+
+/// One requested start: the process, and the share of the runs that fit at
+/// its earliest start which it actually takes.
+#[derive(Debug, Clone, Copy)]
+struct Gene {
+    process: usize,
+    share: f64,
+}
+
+/* Objective first, then the shorter makespan */
+type Fitness = (u64, Reverse<u64>);
 
 #[derive(Debug, Clone)]
-struct Solution {
-    sequence: Vec<String>,
-    fitness: i32,
-    time: u64,
-    log: Vec<(String, u64, u64)>,
+struct Individual {
+    genes: Vec<Gene>,
+    fitness: Fitness,
 }
 
-impl Solution {
-    fn new(sequence: Vec<String>, fitness: i32, time: u64) -> Self {
-        Solution {
-            sequence,
-            fitness,
-            time,
-            log: Vec::new(),
+/// Serial SGS decoding: every gene, in order, is placed at its earliest
+/// feasible start; genes whose process can never start, or whose share rounds
+/// to no run, are skipped.
+fn decode(model: &Model, genes: &[Gene]) -> Timeline {
+    let mut timeline = Timeline::new(model);
+    for gene in genes {
+        if let Some((k, runs)) = timeline.earliest(model, gene.process) {
+            let count = ((gene.share * runs as f64).round() as u64).min(runs);
+            if count > 0 {
+                timeline.place(model, gene.process, count, k);
+            }
         }
     }
+    timeline
 }
 
-pub fn genetic_algorithm(data: Data, max_delay: u32) -> Option<(u64, HashMap<String, u64>, Vec<(String, u64, u64)>)> {
-    let stock_scores = precompute_stock_scores(&data);
-    
-    let population_size = 20000;
-    let generations = 100;
-    let mutation_rate = 0.1;
-
-    let mut population: Vec<Solution> = (0..population_size)
-        .map(|_| {
-            let sequence = data.processes.iter()
-                .map(|p| p.id.clone())
-                .collect();
-            Solution::new(sequence, 0, 0)
-        })
-        .collect();
+fn evaluate(model: &Model, genes: Vec<Gene>) -> Individual {
+    let timeline = decode(model, &genes);
+    let fitness = (model.objective(timeline.stocks()), Reverse(timeline.makespan));
+    Individual { genes, fitness }
+}
 
-    let timer_flag = delay::start_timer(std::time::Duration::from_secs(max_delay as u64));
-    let start = Instant::now();
-    
-    while true {
+/* Decoding dominates a generation, so the population is split over every core */
+fn evaluate_all(model: &Model, chromosomes: Vec<Vec<Gene>>) -> Vec<Individual> {
+    let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+    let chunk = (chromosomes.len() + threads - 1) / threads.max(1);
+    if threads <= 1 || chunk == 0 {
+        return chromosomes.into_iter().map(|genes| evaluate(model, genes)).collect();
+    }
 
-        if timer_flag.load(AtomicOrdering::SeqCst) {
-            println!("Timer elapsed, stopping optimization");
-            break;
-        }
+    let mut chunks = Vec::new();
+    let mut chromosomes = chromosomes;
+    while chromosomes.len() > chunk {
+        let rest = chromosomes.split_off(chunk);
+        chunks.push(chromosomes);
+        chromosomes = rest;
+    }
+    chunks.push(chromosomes);
+
+    std::thread::scope(|scope| {
+        let workers: Vec<_> = chunks.into_iter()
+            .map(|chunk| scope.spawn(move || chunk.into_iter().map(|genes| evaluate(model, genes)).collect::<Vec<_>>()))
+            .collect();
+        workers.into_iter().flat_map(|worker| worker.join().unwrap()).collect()
+    })
+}
 
-        // Evaluate fitness
-        for individual in &mut population {
-            (individual.fitness, individual.time) = evaluate_fitness(&data, &individual.sequence, &stock_scores);
-            individual.log = simulate_and_log(&data, &individual.sequence);
-        }
+/* Random process order and shares, plus a few seeds: the processes in file
+ * order and in SGS priority order, both taking every run that fits */
+fn initial_population(data: &Data, model: &Model, size: usize) -> Vec<Vec<Gene>> {
+    let mut rng = rand::thread_rng();
+    let processes = model.processes.len();
+    let length = (processes * GENES_PER_PROCESS).max(1);
+
+    let in_order: Vec<Gene> = (0..length).map(|i| Gene { process: i % processes, share: 1.0 }).collect();
+    let mut by_priority: Vec<usize> = (0..processes).collect();
+    by_priority.sort_by_key(|&p| -sgs::score_process(&data.processes[p], &data.stocks, &data.objectives));
+    let prioritized: Vec<Gene> = (0..length).map(|i| Gene { process: by_priority[i % processes], share: 1.0 }).collect();
+
+    let mut population = vec![in_order, prioritized];
+    while population.len() < size {
+        let mut genes: Vec<Gene> = (0..length).map(|i| Gene { process: i % processes, share: rng.gen_range(0.0..=1.0) }).collect();
+        genes.shuffle(&mut rng);
+        population.push(genes);
+    }
+    population.truncate(size);
+    population
+}
 
-        // Selection
-        population.sort_by_key(|ind| std::cmp::Reverse(ind.fitness));
-        population.truncate(population_size / 2);
-
-        // Crossover
-        let mut rng = rand::thread_rng();
-        let mut new_population = population.clone();
-        while new_population.len() < population_size {
-            let parent1 = &population[rng.gen_range(0..population.len())];
-            let parent2 = &population[rng.gen_range(0..population.len())];
-            let crossover_point = rng.gen_range(0..parent1.sequence.len());
-            let mut offspring_sequence = parent1.sequence[..crossover_point].to_vec();
-            offspring_sequence.extend_from_slice(&parent2.sequence[crossover_point..]);
-            let mut offspring = Solution::new(offspring_sequence, 0, 0);
-            new_population.push(offspring);
-        }
+fn tournament<'a>(population: &'a [Individual], rng: &mut impl Rng) -> &'a Individual {
+    (0..TOURNAMENT_SIZE)
+        .map(|_| &population[rng.gen_range(0..population.len())])
+        .max_by_key(|individual| individual.fitness)
+        .unwrap()
+}
 
-        // Mutation
-        for individual in &mut new_population {
-            if rng.gen_bool(mutation_rate) {
-                let mutation_point = rng.gen_range(0..individual.sequence.len());
-                let new_process = data.processes[rng.gen_range(0..data.processes.len())].id.clone();
-                individual.sequence[mutation_point] = new_process;
-            }
-        }
+/// Order crossover on lists with repeated processes: a slice of the first
+/// parent is kept in place and the other positions take the second parent's
+/// genes in order, minus one occurrence of each process the slice holds.
+fn order_crossover(first: &[Gene], second: &[Gene], processes: usize, rng: &mut impl Rng) -> Vec<Gene> {
+    let length = first.len();
+    let (mut from, mut to) = (rng.gen_range(0..length), rng.gen_range(0..length));
+    if from > to {
+        std::mem::swap(&mut from, &mut to);
+    }
 
-        population = new_population;
+    let mut kept = vec![0usize; processes];
+    for gene in &first[from..=to] {
+        kept[gene.process] += 1;
     }
+    let mut rest = second.iter().filter(|gene| {
+        if kept[gene.process] > 0 {
+            kept[gene.process] -= 1;
+            false
+        } else {
+            true
+        }
+    });
 
-    let mut best_individual = population.iter().max_by_key(|ind| ind.fitness).unwrap().clone();
-    let (final_stock, time) = simulate(&data, &best_individual.sequence);
-    best_individual.log = simulate_and_log(&data, &best_individual.sequence);
+    (0..length).map(|i| {
+        if (from..=to).contains(&i) { first[i] } else { *rest.next().unwrap() }
+    }).collect()
+}
 
-    Some((time, final_stock, best_individual.log.clone()))
+fn mutate(genes: &mut [Gene], rng: &mut impl Rng) {
+    if rng.gen_bool(MUTATION_RATE) {
+        let (a, b) = (rng.gen_range(0..genes.len()), rng.gen_range(0..genes.len()));
+        genes.swap(a, b);
+    }
+    if rng.gen_bool(MUTATION_RATE) {
+        let at = rng.gen_range(0..genes.len());
+        genes[at].share = rng.gen_range(0.0..=1.0);
+    }
 }
 
-// Function to simulate and log the process execution
-fn simulate_and_log(data: &Data, sequence: &[String]) -> Vec<(String, u64, u64)> {
-    let mut current_stock = data.stocks.clone();
-    let mut total_time = 0;
-    let mut log = Vec::new();
-
-    for process_id in sequence {
-        if let Some(process) = data.processes.iter().find(|p| &p.id == process_id) {
-            if process.input.iter().all(|(item, qty)| current_stock.get(item).unwrap_or(&0) >= qty) {
-                let execution_count = 1; // Here we're assuming that each process is executed once at a time.
-                let start_time = total_time;
-                for (item, qty) in &process.input {
-                    *current_stock.get_mut(item).unwrap() -= qty;
-                }
-                for (item, qty) in &process.output {
-                    *current_stock.entry(item.clone()).or_insert(0) += qty;
-                }
-                total_time += process.time as u64;
-                log.push((process.id.clone(), execution_count, start_time));
-            }
-        }
+/// Genetic algorithm over priority lists decoded by the serial SGS. The
+/// population starts diverse, parents are picked by tournament, children come
+/// from order crossover and mutation, and the best `ELITES` survive as they
+/// are. Runs for `generations` generations, or until the timer when 0.
+pub fn genetic_algorithm(data: Data, max_delay: u32, population_size: usize, generations: u64, progress: &Reporter) -> Option<(u64, HashMap<String, u64>, Vec<(String, u64, u64)>)> {
+    let model = Model::new(&data);
+    if model.processes.is_empty() {
+        return None;
     }
+    let population_size = population_size.max(ELITES + 1);
 
-    log
-}
+    let timer_flag = delay::start_timer(std::time::Duration::from_secs(max_delay as u64));
+    let start = Instant::now();
+    let mut rng = rand::thread_rng();
 
-fn evaluate_fitness(data: &Data, sequence: &[String], stock_scores: &HashMap<String, u64>) -> (i32, u64) {
-    let (stock, time) = simulate(data, sequence);
-    let fit: u64 = (stock.iter().map(|(item, qty)| stock_scores.get(item).unwrap_or(&0) * qty).sum());
-    (-1 * fit as i32, time)
-}
+    let mut population = evaluate_all(&model, initial_population(&data, &model, population_size));
+    let mut generation = 0;
 
-fn simulate(data: &Data, sequence: &[String]) -> (HashMap<String, u64>, u64) {
-    let mut current_stock = data.stocks.clone();
-    let mut total_time = 0;
-
-    for process_id in sequence {
-        if let Some(process) = data.processes.iter().find(|p| &p.id == process_id) {
-            if process.input.iter().all(|(item, qty)| current_stock.get(item).unwrap_or(&0) >= qty) {
-                for (item, qty) in &process.input {
-                    *current_stock.get_mut(item).unwrap() -= qty;
-                }
-                for (item, qty) in &process.output {
-                    *current_stock.entry(item.clone()).or_insert(0) += qty;
-                }
-                total_time += process.time as u64;
-            }
+    while generations == 0 || generation < generations {
+        if timer_flag.load(AtomicOrdering::SeqCst) {
+            say!("Timer elapsed, stopping optimization");
+            break;
         }
+
+        population.sort_by_key(|individual| Reverse(individual.fitness));
+        let (best_objective, Reverse(best_makespan)) = population[0].fitness;
+        generation += 1;
+        progress.tick(generation, best_objective, best_makespan, || {
+            format!("population {}, {:.1} generations/s", population_size, generation as f64 / start.elapsed().as_secs_f64())
+        });
+
+        let mut children = Vec::with_capacity(population_size - ELITES);
+        while children.len() < population_size - ELITES {
+            let first = tournament(&population, &mut rng);
+            let second = tournament(&population, &mut rng);
+            let mut genes = order_crossover(&first.genes, &second.genes, model.processes.len(), &mut rng);
+            mutate(&mut genes, &mut rng);
+            children.push(genes);
+        }
+        let mut next: Vec<Individual> = population[..ELITES].to_vec();
+        next.extend(evaluate_all(&model, children));
+        population = next;
     }
 
-    (current_stock, total_time)
-}
\ No newline at end of file
+    let champion = population.iter().max_by_key(|individual| individual.fitness).unwrap();
+    let (best_objective, Reverse(best_makespan)) = champion.fitness;
+    progress.finish(generation, best_objective, best_makespan, format!("{} generations", generation));
+
+    let elapsed = start.elapsed();
+    say!("Genetic algorithm executed in: {}.{:03} seconds", elapsed.as_secs(), elapsed.subsec_millis());
+    say!("{} generations, {:.1} generations/s\n", generation, generation as f64 / elapsed.as_secs_f64().max(1e-9));
+
+    /* only the champion's log is ever built */
+    let timeline = decode(&model, &champion.genes);
+    Some((timeline.makespan, model.stocks_map(timeline.stocks()), timeline.log(&model)))
+}
@@ -1,211 +1,239 @@
 use crate::Data;
-use crate::Process;
 use crate::delay;
-use crate::stock_scores;
-use std::collections::{HashMap, HashSet};
+use crate::progress::Reporter;
+use crate::search::{Model, Node};
+use std::collections::HashMap;
 use std::time::Instant;
 use std::sync::Arc;
-use std::hash::{Hash, Hasher};
 use std::sync::atomic::{AtomicBool, Ordering as AtomicOrdering};
 
-#[derive(Clone, Eq, PartialEq, Debug)]
-struct StockState(HashMap<String, u64>);
-
-impl Hash for StockState {
-    fn hash<H: Hasher>(&self, state: &mut H) {
-        for (key, value) in &self.0 {
-            key.hash(state);
-            value.hash(state);
-        }
-    }
+/* Past this many entries the table only updates the states it already holds */
+const MAX_TABLE: usize = 2_000_000;
+/* Expansions between two greedy completions, and their length */
+const ROLLOUT_EVERY: u64 = 64;
+const ROLLOUT_STEPS: usize = 1_000;
+
+/* What is known about a state: the smallest g it was expanded at in
+ * `iteration`, and a lower bound on the time from it to a goal */
+struct Entry {
+    g: u64,
+    h: u64,
+    iteration: u64,
 }
 
-impl StockState {
-    fn calculate_heuristic(&self, objectives: &[String], heuristic_scores: &HashMap<String, u64>) -> u64 {
-        objectives.iter().map(|obj| heuristic_scores.get(obj).cloned().unwrap_or(0)).sum()
-    }
+enum Outcome {
+    Found,
+    /* smallest f above the limit seen below the node, u64::MAX if none */
+    Exceeded(u64),
 }
 
-#[derive(Clone, Eq, PartialEq)]
-struct State {
-    time: u64,
-    stocks: StockState,
-    objectives: HashMap<String, u64>,
-    log: Vec<(String, u64, u64)>,
+struct Search<'a> {
+    model: &'a Model,
+    timer_flag: &'a Arc<AtomicBool>,
+    progress: &'a Reporter,
+    table: HashMap<(Vec<u64>, Vec<(u64, usize, u64)>), Entry>,
+    iteration: u64,
+    limit: u64,
+    /* goal: an objective of at least `target` within `limit` */
+    target: u64,
+    /* highest LP bound cut because it was below the target */
+    next_target: u64,
+    exhaustive: bool,
+    expanded: u64,
+    best_objective: u64,
+    best_makespan: u64,
+    best_node: Node,
+    next_rollout: u64,
 }
 
-impl Hash for State {
-    fn hash<H: Hasher>(&self, state: &mut H) {
-        self.time.hash(state);
-        self.stocks.hash(state);
-        for (key, value) in &self.objectives {
-            key.hash(state);
-            value.hash(state);
+impl<'a> Search<'a> {
+    fn record(&mut self, node: &Node) {
+        let objective = node.objective(self.model);
+        if objective > self.best_objective || (objective == self.best_objective && node.makespan < self.best_makespan) {
+            self.best_objective = objective;
+            self.best_makespan = node.makespan;
+            self.best_node = node.clone();
         }
     }
-}
 
-impl State {
-    fn new(time: u64, stocks: HashMap<String, u64>, objectives: &[String], log: Vec<(String, u64, u64)>) -> Self {
-        let mut objectives_map = HashMap::new();
-        for obj in objectives {
-            objectives_map.insert(obj.clone(), *stocks.get(obj).unwrap_or(&0));
+    /* Anytime incumbent: run the largest batches that fit until nothing is left running */
+    fn rollout(&mut self, node: &Node) {
+        self.next_rollout = self.expanded + ROLLOUT_EVERY;
+        let mut node = node.clone();
+        for _ in 0..ROLLOUT_STEPS {
+            if self.timer_flag.load(AtomicOrdering::SeqCst) {
+                break;
+            }
+            for process in 0..self.model.processes.len() {
+                let runs = node.max_runs(self.model, process);
+                if runs > 0 && runs != u64::MAX {
+                    node.start(self.model, process, runs);
+                }
+            }
+            self.record(&node);
+            if !node.advance(self.model) {
+                break;
+            }
         }
-        State { time, stocks: StockState(stocks), objectives: objectives_map, log }
     }
 
-    fn apply_processes(&self, processes: &[Process], objectives: &[String]) -> Vec<Self> {
-        let mut new_states = Vec::new();
-
-        for process in processes {
-            let max_executable_times = process
-                .input
-                .iter()
-                .map(|(input_item, input_amount)| {
-                    self.stocks
-                        .0
-                        .get(input_item)
-                        .map_or(0, |&available| available / input_amount)
-                })
-                .min()
-                .unwrap_or(0);
-
-            for count in 1..=max_executable_times {
-                let mut new_stocks = self.stocks.0.clone();
-                let mut new_log = self.log.clone();
-                let mut valid_state = true;
-
-                for (input_item, input_amount) in &process.input {
-                    if *new_stocks.get(input_item).unwrap() < input_amount * count {
-                        valid_state = false;
-                        break;
-                    }
-                    *new_stocks.get_mut(input_item).unwrap() -= input_amount * count;
-                }
+    fn dfs(&mut self, node: &Node) -> Outcome {
+        if self.timer_flag.load(AtomicOrdering::SeqCst) {
+            return Outcome::Exceeded(u64::MAX);
+        }
 
-                if !valid_state {
-                    continue;
-                }
+        let settled = node.settled(self.model);
+        let mut h = node.remaining_time(self.model, &settled, self.target);
+        let key = node.key();
+        if let Some(entry) = self.table.get(&key) {
+            h = h.max(entry.h);
+            if entry.iteration == self.iteration && entry.g <= node.time {
+                return Outcome::Exceeded(node.time.saturating_add(h));
+            }
+        }
 
-                for (output_item, output_amount) in &process.output {
-                    *new_stocks.entry(output_item.clone()).or_insert(0) += output_amount * count;
-                }
+        let f = node.time.saturating_add(h);
+        if f > self.limit {
+            return Outcome::Exceeded(f);
+        }
 
-                new_log.push((process.id.clone(), count, self.time + process.time * count));
+        self.record(node);
+        if self.model.objective(&settled) >= self.target {
+            /* h is then the running time left, so the makespan fits the limit */
+            return Outcome::Found;
+        }
 
-                let mut new_objectives = self.objectives.clone();
-                for obj in objectives {
-                    *new_objectives.entry(obj.clone()).or_insert(0) = *new_stocks.get(obj).unwrap_or(&0);
-                }
+        let table_full = self.table.len() >= MAX_TABLE;
+        if let Some(entry) = self.table.get_mut(&key) {
+            entry.g = node.time;
+            entry.iteration = self.iteration;
+        } else if !table_full {
+            self.table.insert(key.clone(), Entry { g: node.time, h, iteration: self.iteration });
+        }
 
-                new_states.push(State {
-                    time: self.time + process.time * count,
-                    stocks: StockState(new_stocks),
-                    objectives: new_objectives,
-                    log: new_log,
-                });
+        if self.expanded >= self.next_rollout {
+            self.rollout(node);
+        }
+        self.expanded += 1;
+        let (successors, complete) = node.successors(self.model, self.timer_flag);
+        self.exhaustive &= complete;
+
+        let mut children = Vec::new();
+        for child in successors {
+            let settled = child.settled(self.model);
+            let bound = self.model.upper_bound(&settled);
+            if bound < self.target {
+                self.next_target = self.next_target.max(bound);
+                continue;
+            }
+            let f = child.time.saturating_add(child.remaining_time(self.model, &settled, self.target));
+            children.push((f, std::cmp::Reverse(self.model.objective(&settled)), child));
+        }
+        children.sort_by_key(|(f, objective, _)| (*f, *objective));
+
+        let best_objective = self.best_objective;
+        let best_makespan = self.best_makespan;
+        let (limit, target) = (self.limit, self.target);
+        self.progress.tick(self.expanded, best_objective, best_makespan, || format!("f-limit {} target {}", limit, target));
+
+        let mut exceeded = u64::MAX;
+        for (_, _, child) in children {
+            match self.dfs(&child) {
+                Outcome::Found => return Outcome::Found,
+                Outcome::Exceeded(f) => exceeded = exceeded.min(f),
             }
         }
 
-        new_states
+        /* nothing below reaches the goal under the limit: back the bound up */
+        if !self.timer_flag.load(AtomicOrdering::SeqCst) {
+            if let Some(entry) = self.table.get_mut(&key) {
+                entry.h = entry.h.max(exceeded.saturating_sub(node.time));
+            }
+        }
+        Outcome::Exceeded(exceeded)
     }
 }
 
-pub fn optimize(data: Data, delay: u32) -> Option<(u64, HashMap<String, u64>, Vec<(String, u64, u64)>)> {
-    let heuristic_scores = stock_scores::precompute_stock_scores(&data);
-    let mut visited_global = HashSet::new();
+/// IDA* over the concurrent schedule. Each target objective, starting from the
+/// LP bound of the initial stocks, is searched with an f-limit that grows to
+/// the smallest f that exceeded it; the first goal found therefore has the
+/// shortest makespan for that objective. When no state can reach the target
+/// at all, the target drops to the best bound that was cut. A bounded
+/// transposition table keeps the smallest g per state and backed-up h values
+/// across iterations. Expanded nodes are now and then completed greedily, so
+/// the best schedule seen so far keeps improving and is returned when the
+/// timer elapses.
+pub fn optimize(data: Data, delay: u32, progress: &Reporter) -> Option<(u64, HashMap<String, u64>, Vec<(String, u64, u64)>, bool)> {
+    let model = Model::new(&data);
     let timer_flag = delay::start_timer(std::time::Duration::from_secs(delay as u64));
     let start = Instant::now();
 
-    let initial_state = State::new(0, data.stocks.clone(), &data.objectives, vec![]);
-    let mut threshold = initial_state.stocks.calculate_heuristic(&data.objectives, &heuristic_scores)
-        .max(initial_state.time);
-    let mut best_state: Option<State> = None;
+    let root = Node::root(&model);
+    let mut search = Search {
+        model: &model,
+        timer_flag: &timer_flag,
+        progress,
+        table: HashMap::new(),
+        iteration: 0,
+        limit: 0,
+        target: model.upper_bound(&root.stocks),
+        next_target: 0,
+        exhaustive: true,
+        expanded: 0,
+        best_objective: root.objective(&model),
+        best_makespan: 0,
+        best_node: root.clone(),
+        next_rollout: 0,
+    };
+    let mut proven = false;
+
+    /* an unbounded relaxation gives no target to aim at: keep raising it past the incumbent */
+    let bounded = search.target != u64::MAX;
+    if !bounded {
+        search.target = search.best_objective + 1;
+    }
+    search.limit = root.remaining_time(&model, &root.settled(&model), search.target);
 
     loop {
         if timer_flag.load(AtomicOrdering::SeqCst) {
-            println!("Timer elapsed, stopping optimization");
-            break;
-        }
-
-        visited_global.clear();
-
-        let (result, new_threshold) = depth_limited_search(
-            initial_state.clone(),
-            &data,
-            &heuristic_scores,
-            threshold,
-            &mut best_state,
-            &mut visited_global,
-            &timer_flag
-        );
-
-        if let Some(final_state) = result {
-            best_state = Some(final_state);
-        }
-
-        if new_threshold == u64::MAX || new_threshold == threshold {
+            say!("Timer elapsed, stopping optimization");
             break;
         }
 
-        threshold = new_threshold;
-    }
-
-    let elapsed = start.elapsed();
-    println!("IDA* executed in: {}.{:03} seconds\n", elapsed.as_secs(), elapsed.subsec_millis());
-
-    best_state.map(|state| (state.time, state.stocks.0, state.log))
-}
-
-fn depth_limited_search(
-    state: State,
-    data: &Data,
-    heuristic_scores: &HashMap<String, u64>,
-    limit: u64,
-    best_state: &mut Option<State>,
-    visited_global: &mut HashSet<(StockState, u64)>,
-    timer_flag: &Arc<AtomicBool>
-) -> (Option<State>, u64) {
-    let f_value = state.time + state.stocks.calculate_heuristic(&data.objectives, heuristic_scores);
-
-    if f_value > limit {
-        return (None, f_value);
-    }
-
-    if timer_flag.load(AtomicOrdering::SeqCst) {
-        return (None, u64::MAX);
-    }
-
-    if visited_global.contains(&(state.stocks.clone(), state.time)) {
-        return (None, u64::MAX);
-    }
-
-    visited_global.insert((state.stocks.clone(), state.time));
-
-    if let Some(ref best) = best_state {
-        if state.objectives.values().sum::<u64>() > best.objectives.values().sum::<u64>() {
-            *best_state = Some(state.clone());
-        } else if state.objectives.values().sum::<u64>() == best.objectives.values().sum::<u64>() && state.time < best.time {
-            *best_state = Some(state.clone());
+        search.iteration += 1;
+        search.next_target = 0;
+        match search.dfs(&root) {
+            Outcome::Found if bounded => {
+                proven = search.exhaustive && !timer_flag.load(AtomicOrdering::SeqCst);
+                break;
+            }
+            Outcome::Found => {
+                search.target = search.best_objective + 1;
+                search.table.clear();
+                search.limit = root.remaining_time(&model, &root.settled(&model), search.target);
+            }
+            Outcome::Exceeded(f) if f != u64::MAX => search.limit = f.max(search.limit + 1),
+            Outcome::Exceeded(_) => {
+                if timer_flag.load(AtomicOrdering::SeqCst) {
+                    continue;
+                }
+                /* the target is out of reach: aim at the best objective still possible */
+                let target = search.next_target.max(search.best_objective);
+                if target >= search.target {
+                    break;
+                }
+                search.target = target;
+                search.table.clear();
+                search.limit = root.remaining_time(&model, &root.settled(&model), target);
+            }
         }
-    } else {
-        *best_state = Some(state.clone());
     }
 
-    let mut min_threshold = u64::MAX;
-    let mut local_best_state = None;
-
-    for new_state in state.apply_processes(&data.processes, &data.objectives) {
-        let (result, threshold) = depth_limited_search(new_state.clone(), data, heuristic_scores, limit, best_state, visited_global, timer_flag);
-
-        if let Some(r) = result {
-            local_best_state = Some(r);
-        }
+    progress.finish(search.expanded, search.best_objective, search.best_makespan, (if proven { "proven optimal" } else { "not proven" }).to_string());
 
-        if threshold < min_threshold {
-            min_threshold = threshold;
-        }
-    }
+    let elapsed = start.elapsed();
+    say!("IDA* executed in: {}.{:03} seconds\n", elapsed.as_secs(), elapsed.subsec_millis());
 
-    (local_best_state.or(best_state.clone()), min_threshold)
+    let best = search.best_node;
+    Some((best.makespan, model.stocks_map(&best.settled(&model)), best.log, proven))
 }
@@ -0,0 +1,469 @@
+use crate::Data;
+use crate::lp;
+use crate::search::Model;
+use crate::simulator::Log;
+use std::collections::HashMap;
+use std::io::{self, Write};
+
+/* Past this many tableau cells the time-indexed relaxation is not solved with
+ * the dense simplex, and bounds fall back to the time-free relaxation */
+const MAX_RELAXATION_CELLS: usize = 4_000_000;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    Lp,
+    Mps,
+}
+
+/// Time-indexed MILP of an instance: `x<p>_t<time>` is the number of runs of
+/// process `p` started at `time`, and `s<i>_t<time>` the stock of item `i`
+/// once the starts at `time` are made. Time is cut in buckets of `bucket`
+/// cycles and every run has to end by `horizon`.
+pub struct TimeIndexed {
+    pub model: Model,
+    pub bucket: u64,
+    pub horizon: u64,
+    /* duration of each process, in buckets */
+    durations: Vec<u64>,
+}
+
+/// Largest bucket every process time is a whole number of: their gcd.
+pub fn default_bucket(data: &Data) -> u64 {
+    fn gcd(a: u64, b: u64) -> u64 {
+        if b == 0 { a } else { gcd(b, a % b) }
+    }
+    data.processes.iter().map(|process| process.time).fold(0, gcd).max(1)
+}
+
+/// Horizon when neither the file nor the command line gives one: ten times
+/// the longest process.
+pub fn default_horizon(data: &Data) -> u64 {
+    10 * data.processes.iter().map(|process| process.time).max().unwrap_or(1).max(1)
+}
+
+pub fn start_variable(process: usize, time: u64) -> String {
+    format!("x{}_t{}", process, time)
+}
+
+/// Inverse of `start_variable`: the process index and start time.
+pub fn parse_start_variable(name: &str) -> Option<(usize, u64)> {
+    let (process, time) = name.strip_prefix('x')?.split_once("_t")?;
+    Some((process.parse().ok()?, time.parse().ok()?))
+}
+
+fn stock_variable(item: usize, time: u64) -> String {
+    format!("s{}_t{}", item, time)
+}
+
+fn parse_stock_variable(name: &str) -> Option<(usize, u64)> {
+    let (item, time) = name.strip_prefix('s')?.split_once("_t")?;
+    Some((item.parse().ok()?, time.parse().ok()?))
+}
+
+fn coefficient(value: f64) -> String {
+    if value.fract() == 0.0 { format!("{}", value as i64) } else { format!("{}", value) }
+}
+
+impl TimeIndexed {
+    pub fn new(data: &Data, horizon: u64, bucket: u64) -> Self {
+        let bucket = bucket.max(1);
+        TimeIndexed {
+            model: Model::new(data),
+            horizon: horizon - horizon % bucket,
+            durations: data.processes.iter().map(|process| process.time.div_ceil(bucket)).collect(),
+            bucket,
+        }
+    }
+
+    fn buckets(&self) -> u64 {
+        self.horizon / self.bucket
+    }
+
+    /* Start buckets of a process such that its run ends by the horizon */
+    fn starts(&self, process: usize) -> std::ops::RangeInclusive<u64> {
+        0..=self.buckets().saturating_sub(self.durations[process])
+    }
+
+    fn runnable(&self, process: usize) -> bool {
+        self.durations[process] <= self.buckets()
+    }
+
+    /* Net change of `item` in bucket `k` as (coefficient, process, start
+     * bucket): inputs of the runs started at `k` and outputs of the runs
+     * ending at `k`, merged when a run takes no time */
+    fn balance(&self, item: usize, k: u64) -> Vec<(f64, usize, u64)> {
+        let mut terms: Vec<(f64, usize, u64)> = Vec::new();
+        let mut add = |value: f64, p: usize, start: u64| {
+            if value == 0.0 {
+                return;
+            }
+            match terms.iter_mut().find(|(_, q, at)| *q == p && *at == start) {
+                Some(term) => term.0 += value,
+                None => terms.push((value, p, start)),
+            }
+        };
+        for (p, step) in self.model.processes.iter().enumerate() {
+            if !self.runnable(p) {
+                continue;
+            }
+            if self.starts(p).contains(&k) {
+                add(-step.input.iter().filter(|(i, _)| *i == item).map(|(_, qty)| *qty as f64).sum::<f64>(), p, k);
+            }
+            if k >= self.durations[p] && self.starts(p).contains(&(k - self.durations[p])) {
+                add(step.output.iter().filter(|(i, _)| *i == item).map(|(_, qty)| *qty as f64).sum(), p, k - self.durations[p]);
+            }
+        }
+        terms.retain(|(value, _, _)| *value != 0.0);
+        terms
+    }
+
+    /* `s_k - s_{k-1} - balance = 0`, or `s_0 - balance = initial` */
+    fn rows(&self) -> Vec<(String, Vec<(f64, String)>, f64)> {
+        let mut rows = Vec::new();
+        for item in 0..self.model.names.len() {
+            for k in 0..=self.buckets() {
+                let mut terms = vec![(1.0, stock_variable(item, k * self.bucket))];
+                if k > 0 {
+                    terms.push((-1.0, stock_variable(item, (k - 1) * self.bucket)));
+                }
+                for (value, p, start) in self.balance(item, k) {
+                    terms.push((-value, start_variable(p, start * self.bucket)));
+                }
+                let rhs = if k == 0 { self.model.initial[item] as f64 } else { 0.0 };
+                rows.push((format!("b{}_t{}", item, k * self.bucket), terms, rhs));
+            }
+        }
+        rows
+    }
+
+    fn objective(&self) -> Vec<String> {
+        self.model.objectives.iter().map(|&item| stock_variable(item, self.horizon)).collect()
+    }
+
+    fn start_variables(&self) -> Vec<String> {
+        (0..self.model.processes.len())
+            .filter(|&p| self.runnable(p))
+            .flat_map(|p| self.starts(p).map(move |k| (p, k)))
+            .map(|(p, k)| start_variable(p, k * self.bucket))
+            .collect()
+    }
+
+    fn legend(&self, out: &mut dyn Write, comment: &str) -> io::Result<()> {
+        writeln!(out, "{} krpsim time-indexed model: horizon {}, bucket {}", comment, self.horizon, self.bucket)?;
+        for (p, step) in self.model.processes.iter().enumerate() {
+            writeln!(out, "{} x{} = {}", comment, p, step.id)?;
+        }
+        for (item, name) in self.model.names.iter().enumerate() {
+            writeln!(out, "{} s{} = {}", comment, item, name)?;
+        }
+        Ok(())
+    }
+
+    /// CPLEX LP format.
+    pub fn write_lp(&self, out: &mut dyn Write) -> io::Result<()> {
+        self.legend(out, "\\")?;
+        writeln!(out, "Maximize")?;
+        let objective = self.objective();
+        if objective.is_empty() {
+            writeln!(out, " obj: 0 {}", stock_variable(0, 0))?;
+        } else {
+            writeln!(out, " obj: {}", objective.join(" + "))?;
+        }
+        writeln!(out, "Subject To")?;
+        for (name, terms, rhs) in self.rows() {
+            let mut line = format!(" {}:", name);
+            for (i, (value, variable)) in terms.iter().enumerate() {
+                match (i, *value < 0.0) {
+                    (0, false) => {}
+                    (_, true) => line += " -",
+                    (_, false) => line += " +",
+                }
+                if value.abs() != 1.0 {
+                    line += &format!(" {}", coefficient(value.abs()));
+                }
+                line += &format!(" {}", variable);
+            }
+            writeln!(out, "{} = {}", line, coefficient(rhs))?;
+        }
+        writeln!(out, "General")?;
+        for variable in self.start_variables() {
+            writeln!(out, " {}", variable)?;
+        }
+        writeln!(out, "End")
+    }
+
+    /// Free MPS format, the same model as `write_lp`.
+    pub fn write_mps(&self, out: &mut dyn Write) -> io::Result<()> {
+        self.legend(out, "*")?;
+        writeln!(out, "NAME krpsim")?;
+        writeln!(out, "OBJSENSE")?;
+        writeln!(out, "    MAX")?;
+        writeln!(out, "ROWS")?;
+        writeln!(out, " N obj")?;
+        let rows = self.rows();
+        for (name, _, _) in &rows {
+            writeln!(out, " E {}", name)?;
+        }
+
+        /* column-major: every variable with the rows it appears in */
+        let mut columns: Vec<(String, Vec<(String, f64)>)> = Vec::new();
+        let mut index = HashMap::new();
+        for variable in self.start_variables() {
+            index.insert(variable.clone(), columns.len());
+            columns.push((variable, Vec::new()));
+        }
+        let integers = columns.len();
+        for (name, terms, _) in &rows {
+            for (value, variable) in terms {
+                let at = *index.entry(variable.clone()).or_insert_with(|| {
+                    columns.push((variable.clone(), Vec::new()));
+                    columns.len() - 1
+                });
+                columns[at].1.push((name.clone(), *value));
+            }
+        }
+        for variable in self.objective() {
+            if let Some(&at) = index.get(&variable) {
+                columns[at].1.push(("obj".to_string(), 1.0));
+            }
+        }
+
+        writeln!(out, "COLUMNS")?;
+        writeln!(out, "    MARKER 'MARKER' 'INTORG'")?;
+        for (i, (variable, entries)) in columns.iter().enumerate() {
+            if i == integers {
+                writeln!(out, "    MARKER 'MARKER' 'INTEND'")?;
+            }
+            for (row, value) in entries {
+                writeln!(out, "    {} {} {}", variable, row, coefficient(*value))?;
+            }
+        }
+        if integers == columns.len() {
+            writeln!(out, "    MARKER 'MARKER' 'INTEND'")?;
+        }
+        writeln!(out, "RHS")?;
+        for (name, _, rhs) in &rows {
+            if *rhs != 0.0 {
+                writeln!(out, "    RHS {} {}", name, coefficient(*rhs))?;
+            }
+        }
+        /* some readers default integer columns to binaries */
+        writeln!(out, "BOUNDS")?;
+        for (variable, _) in &columns[..integers] {
+            writeln!(out, " PL BND {}", variable)?;
+        }
+        writeln!(out, "ENDATA")
+    }
+
+    pub fn write(&self, format: Format, out: &mut dyn Write) -> io::Result<()> {
+        match format {
+            Format::Lp => self.write_lp(out),
+            Format::Mps => self.write_mps(out),
+        }
+    }
+
+    /// Objective bound of the LP relaxation, with the stock variables
+    /// eliminated: for every item and bucket, what was consumed so far minus
+    /// what has arrived may not exceed the initial stock. None when the
+    /// tableau would be too large for the dense simplex.
+    pub fn relaxation_bound(&self) -> Option<u64> {
+        let buckets = self.buckets() as usize + 1;
+        let columns: usize = (0..self.model.processes.len())
+            .filter(|&p| self.runnable(p))
+            .map(|p| (self.buckets() - self.durations[p] + 1) as usize)
+            .sum();
+        let rows = self.model.names.len().saturating_mul(buckets);
+        if rows.saturating_add(1).saturating_mul(columns.saturating_add(rows)) > MAX_RELAXATION_CELLS {
+            return None;
+        }
+        let starts: Vec<(usize, u64)> = (0..self.model.processes.len())
+            .filter(|&p| self.runnable(p))
+            .flat_map(|p| self.starts(p).map(move |k| (p, k)))
+            .collect();
+
+        let net = |p: usize, item: usize| -> f64 {
+            let step = &self.model.processes[p];
+            let produced: u64 = step.output.iter().filter(|(i, _)| *i == item).map(|(_, qty)| qty).sum();
+            let consumed: u64 = step.input.iter().filter(|(i, _)| *i == item).map(|(_, qty)| qty).sum();
+            produced as f64 - consumed as f64
+        };
+        let c: Vec<f64> = starts.iter()
+            .map(|&(p, _)| self.model.objectives.iter().map(|&item| net(p, item)).sum())
+            .collect();
+
+        let mut a = Vec::new();
+        let mut b = Vec::new();
+        for item in 0..self.model.names.len() {
+            if !self.model.processes.iter().any(|step| step.input.iter().any(|(i, _)| *i == item)) {
+                continue;
+            }
+            for k in 0..buckets as u64 {
+                let row: Vec<f64> = starts.iter().map(|&(p, start)| {
+                    let step = &self.model.processes[p];
+                    let mut value = 0.0;
+                    if start <= k {
+                        value += step.input.iter().filter(|(i, _)| *i == item).map(|(_, qty)| *qty as f64).sum::<f64>();
+                    }
+                    if start + self.durations[p] <= k {
+                        value -= step.output.iter().filter(|(i, _)| *i == item).map(|(_, qty)| *qty as f64).sum::<f64>();
+                    }
+                    value
+                }).collect();
+                a.push(row);
+                b.push(self.model.initial[item] as f64);
+            }
+        }
+
+        match lp::maximize(&c, &a, &b).bound() {
+            u64::MAX => None,
+            gain => Some(self.model.objective(&self.model.initial).saturating_add(gain)),
+        }
+    }
+}
+
+/// Best available bound on the objective of a schedule ending by `horizon`:
+/// the time-indexed relaxation when it is small enough to solve, the
+/// time-free one otherwise.
+pub fn objective_bound(data: &Data, horizon: u64) -> Option<u64> {
+    let model = TimeIndexed::new(data, horizon, default_bucket(data));
+    model.relaxation_bound().or_else(|| match model.model.upper_bound(&model.model.initial) {
+        u64::MAX => None,
+        bound => Some(bound),
+    })
+}
+
+/// `variable=value` pairs of a `.sol` file. Blank lines and `#` comments are
+/// skipped; `variable value` is accepted too, as most solvers write it.
+pub fn read_solution(text: &str) -> Result<Vec<(String, f64)>, String> {
+    let mut values = Vec::new();
+    for (number, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (name, value) = match line.split_once('=') {
+            Some((name, value)) => (name.trim(), value.trim()),
+            None => match line.split_whitespace().collect::<Vec<_>>()[..] {
+                [name, value] => (name, value),
+                _ => return Err(format!("line {}: expected variable=value, got '{}'", number + 1, line)),
+            },
+        };
+        let value: f64 = value.parse().map_err(|_| format!("line {}: '{}' is not a number", number + 1, value))?;
+        values.push((name.to_string(), value));
+    }
+    Ok(values)
+}
+
+/// Solution of the exported model mapped back to a schedule: one log entry per
+/// nonzero start variable, the finish time those starts imply, and the final
+/// stocks the solver claims (the stock variables at the latest time listed,
+/// omitted ones being zero), so the simulator can check them. Warnings cover
+/// rounded or unknown variables.
+pub fn solution_log(data: &Data, values: &[(String, f64)]) -> (Log, u64, Option<HashMap<String, u64>>, Vec<String>) {
+    let model = Model::new(data);
+    let mut log = Vec::new();
+    let mut finish = 0;
+    let mut stocks: Vec<(usize, u64, u64)> = Vec::new();
+    let mut warnings = Vec::new();
+
+    for (name, value) in values {
+        let count = value.round();
+        if (value - count).abs() > 1e-6 {
+            warnings.push(format!("{} = {} is fractional, rounded to {}", name, value, count));
+        }
+        if count < 0.0 {
+            warnings.push(format!("{} = {} is negative, ignored", name, value));
+            continue;
+        }
+        let count = count as u64;
+
+        if let Some((process, time)) = parse_start_variable(name) {
+            let Some(step) = data.processes.get(process) else {
+                warnings.push(format!("{}: no process x{} in this file", name, process));
+                continue;
+            };
+            if count > 0 {
+                log.push((step.id.clone(), count, time));
+                finish = finish.max(time + step.time);
+            }
+        } else if let Some((item, time)) = parse_stock_variable(name) {
+            if item < model.names.len() {
+                stocks.push((item, time, count));
+            } else {
+                warnings.push(format!("{}: no stock s{} in this file", name, item));
+            }
+        } else if name != "obj" {
+            warnings.push(format!("{}: not a variable of the exported model", name));
+        }
+    }
+
+    log.sort_by_key(|(_, _, time)| *time);
+    let claimed = stocks.iter().map(|&(_, time, _)| time).max().map(|horizon| {
+        let mut claimed: HashMap<String, u64> = model.names.iter().map(|name| (name.clone(), 0)).collect();
+        for &(item, _, count) in stocks.iter().filter(|&&(_, time, _)| time == horizon) {
+            claimed.insert(model.names[item].clone(), count);
+        }
+        claimed
+    });
+    (log, finish, claimed, warnings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Process;
+
+    #[test]
+    fn both_line_forms() {
+        let values = read_solution("# solved\nx0_t0=2\n\nx1_t5 1\n  s0_t8 =  3.5  \nobj 4\n").unwrap();
+        assert_eq!(values, vec![
+            ("x0_t0".to_string(), 2.0),
+            ("x1_t5".to_string(), 1.0),
+            ("s0_t8".to_string(), 3.5),
+            ("obj".to_string(), 4.0),
+        ]);
+    }
+
+    #[test]
+    fn malformed_lines() {
+        assert!(read_solution("x0_t0 = two\n").unwrap_err().starts_with("line 1"));
+        assert!(read_solution("x0_t0=1\nx0_t1 1 2\n").unwrap_err().starts_with("line 2"));
+    }
+
+    #[test]
+    fn start_variables_round_trip() {
+        assert_eq!(parse_start_variable(&start_variable(3, 120)), Some((3, 120)));
+        assert_eq!(parse_start_variable("s3_t120"), None);
+    }
+
+    #[test]
+    fn mps_integer_markers() {
+        let data = crate::load_data("resources/simple").unwrap();
+        let mut out = Vec::new();
+        TimeIndexed::new(&data, 60, default_bucket(&data)).write_mps(&mut out).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = text.lines().map(str::trim).collect();
+        let start = lines.iter().position(|&line| line == "MARKER 'MARKER' 'INTORG'").unwrap();
+        let end = lines.iter().position(|&line| line == "MARKER 'MARKER' 'INTEND'").unwrap();
+        assert!(start < end);
+        /* only start variables are integer */
+        assert!(lines[start + 1..end].iter().all(|line| line.starts_with('x')));
+        assert!(!lines.iter().any(|line| line.starts_with("MARKER MARKER")));
+    }
+
+    #[test]
+    fn solution_to_log() {
+        let data = Data {
+            stocks: HashMap::from([("a".to_string(), 2)]),
+            processes: vec![Process { id: "make_b".to_string(), input: vec![("a".to_string(), 1)], output: vec![("b".to_string(), 1)], time: 5 }],
+            objectives: vec!["b".to_string()],
+            horizon: None,
+        };
+        let values = read_solution("x0_t0=1.0000001\nx0_t3 1\nx0_t4=0\nx7_t0=1\n").unwrap();
+        let (log, finish, claimed, warnings) = solution_log(&data, &values);
+        assert_eq!(log, vec![("make_b".to_string(), 1, 0), ("make_b".to_string(), 1, 3)]);
+        assert_eq!(finish, 8);
+        assert!(claimed.is_none());
+        assert_eq!(warnings.len(), 1);
+    }
+}
@@ -0,0 +1,299 @@
+use crate::Data;
+use crate::delay;
+use crate::sgs;
+use crate::progress::Reporter;
+use crate::search::{self, Model, Node};
+use crate::simulator::{Log, Schedule};
+use rand::Rng;
+use rand::seq::SliceRandom;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering as AtomicOrdering};
+use std::time::Instant;
+
+/* Iterations between two updates of the operator weights */
+const SEGMENT: u64 = 100;
+/* Share of a weight replaced by the last segment's performance */
+const REACTION: f64 = 0.2;
+/* Operator rewards: new best, better than the current, accepted anyway */
+const NEW_BEST: f64 = 33.0;
+const IMPROVED: f64 = 9.0;
+const ACCEPTED: f64 = 3.0;
+/* Probability of accepting a worse schedule, to leave plateaus */
+const WORSE_ACCEPTANCE: f64 = 0.05;
+/* Completions the exact repair branches over, and starting sets per completion */
+const EXACT_DEPTH: usize = 3;
+const EXACT_BRANCHING: usize = 6;
+/* Completions simulated by the greedy repair before it is cut */
+const FILL_COMPLETIONS: usize = 1_000;
+
+/// How destroy and repair operators are picked.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Selection {
+    /// Roulette wheel on weights that follow each operator's recent success
+    Adaptive,
+    /// Every operator equally often
+    Uniform,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Destroy {
+    /// Every run starting in a random time window
+    Window,
+    /// Every run of one process
+    Process,
+    /// Every run consuming or producing one stock
+    Stock,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Repair {
+    /// Parallel SGS in a random priority order
+    Greedy,
+    /// Best of a few completions of starting sets, from the combination generator
+    Exact,
+}
+
+const DESTROYS: [Destroy; 3] = [Destroy::Window, Destroy::Process, Destroy::Stock];
+const REPAIRS: [Repair; 2] = [Repair::Greedy, Repair::Exact];
+
+/// Operator weights of the adaptive selection, with the rewards collected
+/// since the last update.
+struct Roulette {
+    weights: Vec<f64>,
+    rewards: Vec<f64>,
+    uses: Vec<u64>,
+}
+
+impl Roulette {
+    fn new(operators: usize) -> Self {
+        Roulette { weights: vec![1.0; operators], rewards: vec![0.0; operators], uses: vec![0; operators] }
+    }
+
+    fn pick(&mut self, selection: Selection, rng: &mut impl Rng) -> usize {
+        let picked = match selection {
+            Selection::Uniform => rng.gen_range(0..self.weights.len()),
+            Selection::Adaptive => {
+                let mut at = rng.gen::<f64>() * self.weights.iter().sum::<f64>();
+                self.weights.iter().position(|&weight| { at -= weight; at < 0.0 }).unwrap_or(self.weights.len() - 1)
+            }
+        };
+        self.uses[picked] += 1;
+        picked
+    }
+
+    fn reward(&mut self, operator: usize, reward: f64) {
+        self.rewards[operator] += reward;
+    }
+
+    /* Unused operators keep their weight, and no weight falls to zero */
+    fn update(&mut self) {
+        for operator in 0..self.weights.len() {
+            if self.uses[operator] > 0 {
+                let performance = self.rewards[operator] / self.uses[operator] as f64;
+                self.weights[operator] = ((1.0 - REACTION) * self.weights[operator] + REACTION * performance).max(0.1);
+            }
+            self.rewards[operator] = 0.0;
+            self.uses[operator] = 0;
+        }
+    }
+}
+
+/// Removes part of the schedule. Returns what is kept, and the window
+/// `[from, to)` the repair should fill.
+fn destroy(model: &Model, schedule: &Schedule, operator: Destroy, rng: &mut impl Rng) -> (Log, u64, u64) {
+    let index: HashMap<&str, usize> = model.processes.iter().enumerate().map(|(p, step)| (step.id.as_str(), p)).collect();
+    let process_of = |id: &str| index.get(id).copied();
+    let longest = model.processes.iter().map(|step| step.time).max().unwrap_or(1).max(1);
+
+    let removed: Box<dyn Fn(usize, u64) -> bool> = match operator {
+        Destroy::Window => {
+            let width = longest.max((schedule.makespan as f64 * rng.gen_range(0.1..0.3)) as u64);
+            let from = rng.gen_range(0..=schedule.makespan);
+            Box::new(move |_, start| start >= from && start < from + width)
+        }
+        Destroy::Process => {
+            let chosen = schedule.log.choose(rng).and_then(|(id, _, _)| process_of(id)).unwrap_or_else(|| rng.gen_range(0..model.processes.len()));
+            Box::new(move |p, _| p == chosen)
+        }
+        Destroy::Stock => {
+            let step = &model.processes[schedule.log.choose(rng).and_then(|(id, _, _)| process_of(id)).unwrap_or_else(|| rng.gen_range(0..model.processes.len()))];
+            let (item, _) = *step.input.iter().chain(step.output.iter()).collect::<Vec<_>>().choose(rng).copied().unwrap_or(&(usize::MAX, 0));
+            Box::new(move |p, _| {
+                let step = &model.processes[p];
+                step.input.iter().chain(step.output.iter()).any(|&(other, _)| other == item)
+            })
+        }
+    };
+
+    let mut kept = Vec::new();
+    let (mut from, mut to) = (u64::MAX, 0);
+    for (id, count, start) in &schedule.log {
+        match process_of(id) {
+            Some(p) if removed(p, *start) => {
+                from = from.min(*start);
+                to = to.max(start + model.processes[p].time);
+            }
+            _ => kept.push((id.clone(), *count, *start)),
+        }
+    }
+    /* nothing matched: the repair gets a window of its own to fill */
+    if from > to {
+        from = rng.gen_range(0..=schedule.makespan);
+        to = from + longest;
+    }
+    (kept, from, to)
+}
+
+/* The kept runs starting before `from`, replayed up to `from`; runs whose
+ * inputs went with the destroyed part are left out */
+fn prefix(model: &Model, kept: &Log, from: u64) -> Node {
+    let index: HashMap<&str, usize> = model.processes.iter().enumerate().map(|(p, step)| (step.id.as_str(), p)).collect();
+    let mut node = Node::root(model);
+    for (id, count, start) in kept.iter().take_while(|(_, _, start)| *start < from) {
+        node.release_until(model, *start);
+        let Some(&p) = index.get(id.as_str()) else {
+            continue;
+        };
+        if node.max_runs(model, p) >= *count {
+            node.start(model, p, *count);
+        }
+    }
+    node.release_until(model, from);
+    node
+}
+
+/* Starts what fits in a random priority order, completion after completion, until `to` */
+fn fill_greedy(model: &Model, mut node: Node, to: u64, rng: &mut impl Rng) -> Node {
+    let mut order: Vec<usize> = (0..model.processes.len()).collect();
+    order.shuffle(rng);
+    for _ in 0..FILL_COMPLETIONS {
+        if node.time >= to {
+            break;
+        }
+        for &process in &order {
+            let runs = match node.max_runs(model, process) {
+                u64::MAX => 1,
+                runs => runs,
+            };
+            if runs > 0 {
+                let count = if rng.gen_bool(0.5) { runs } else { rng.gen_range(1..=runs) };
+                node.start(model, process, count);
+            }
+        }
+        if !node.advance(model) {
+            break;
+        }
+    }
+    node
+}
+
+/// Depth-first search over the starting sets of the next `EXACT_DEPTH`
+/// completions before `to`, each leaf completed with the kept runs from
+/// `from` on. The best completed schedule is returned.
+fn fill_exact(data: &Data, model: &Model, node: &Node, to: u64, suffix: &Log, depth: usize, timer_flag: &Arc<AtomicBool>) -> Schedule {
+    let complete = |node: &Node| {
+        let mut log = node.log.clone();
+        log.extend(suffix.iter().cloned());
+        Schedule::new(data, log)
+    };
+    if depth == 0 || node.time >= to || timer_flag.load(AtomicOrdering::SeqCst) {
+        return complete(node);
+    }
+
+    let (combinations, _) = node.combinations(model, timer_flag);
+    let mut best: Option<Schedule> = None;
+    for combination in search::spread(combinations, EXACT_BRANCHING) {
+        let candidate = match node.child(model, &combination) {
+            Some(child) => fill_exact(data, model, &child, to, suffix, depth - 1, timer_flag),
+            /* nothing runs anymore: the starts are all there is */
+            None => {
+                let mut last = node.clone();
+                for &(process, count) in &combination {
+                    last.start(model, process, count);
+                }
+                complete(&last)
+            }
+        };
+        if best.as_ref().is_none_or(|best| candidate.better_than(best)) {
+            best = Some(candidate);
+        }
+    }
+    best.unwrap_or_else(|| complete(node))
+}
+
+/// Large neighborhood search: every iteration destroys part of the current
+/// schedule (a time window, one process, or one stock) and rebuilds it with
+/// the greedy SGS or a small exact search over starting sets. Operators are
+/// picked by adaptive weights (ALNS) or uniformly. Better schedules are always
+/// accepted, equal ones too, and worse ones rarely.
+pub fn optimize(data: Data, delay: u32, selection: Selection, progress: &Reporter) -> Option<(u64, HashMap<String, u64>, Vec<(String, u64, u64)>)> {
+    let model = Model::new(&data);
+    if model.processes.is_empty() {
+        return None;
+    }
+    let timer_flag = delay::start_timer(std::time::Duration::from_secs(delay as u64));
+    let start = Instant::now();
+    let mut rng = rand::thread_rng();
+
+    let idle = Schedule::new(&data, Vec::new());
+    let seeded = Schedule::new(&data, sgs::greedy_log(&data));
+    let mut current = if seeded.better_than(&idle) { seeded } else { idle };
+    let mut best = current.clone();
+
+    let mut destroys = Roulette::new(DESTROYS.len());
+    let mut repairs = Roulette::new(REPAIRS.len());
+    let mut iterations: u64 = 0;
+
+    while !timer_flag.load(AtomicOrdering::SeqCst) {
+        let d = destroys.pick(selection, &mut rng);
+        let r = repairs.pick(selection, &mut rng);
+
+        let (kept, from, to) = destroy(&model, &current, DESTROYS[d], &mut rng);
+        let suffix: Log = kept.iter().filter(|(_, _, start)| *start >= from).cloned().collect();
+        /* with nothing kept after the window, the repair builds the whole end */
+        let to = if suffix.is_empty() { u64::MAX } else { to };
+        let node = prefix(&model, &kept, from);
+        let candidate = match REPAIRS[r] {
+            Repair::Greedy => {
+                let mut log = fill_greedy(&model, node, to, &mut rng).log;
+                log.extend(suffix);
+                Schedule::new(&data, log)
+            }
+            Repair::Exact => fill_exact(&data, &model, &node, to, &suffix, EXACT_DEPTH, &timer_flag),
+        };
+
+        let reward = if candidate.better_than(&best) {
+            best = candidate.clone();
+            current = candidate;
+            NEW_BEST
+        } else if candidate.better_than(&current) {
+            current = candidate;
+            IMPROVED
+        } else if !current.better_than(&candidate) || rng.gen_bool(WORSE_ACCEPTANCE) {
+            current = candidate;
+            ACCEPTED
+        } else {
+            0.0
+        };
+        destroys.reward(d, reward);
+        repairs.reward(r, reward);
+
+        iterations += 1;
+        if iterations.is_multiple_of(SEGMENT) {
+            destroys.update();
+            repairs.update();
+        }
+        progress.tick(iterations, best.objective, best.makespan, || {
+            format!("destroy {:.1?} repair {:.1?}", destroys.weights, repairs.weights)
+        });
+    }
+
+    progress.finish(iterations, best.objective, best.makespan, format!("{} iterations", iterations));
+
+    let elapsed = start.elapsed();
+    say!("LNS executed in: {}.{:03} seconds", elapsed.as_secs(), elapsed.subsec_millis());
+    say!("{} iterations, destroy weights {:.2?}, repair weights {:.2?}\n", iterations, destroys.weights, repairs.weights);
+
+    Some((best.makespan, best.stocks, best.log))
+}
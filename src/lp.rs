@@ -0,0 +1,136 @@
+/* Dense primal simplex for `max c.x  s.t.  A.x <= b, x >= 0` with `b >= 0`,
+ * which is the shape of every relaxation in krpsim: stocks are never negative,
+ * so the all-slack basis is always a feasible start. */
+
+const EPSILON: f64 = 1e-9;
+
+#[derive(Debug, Clone)]
+pub enum Solution {
+    Optimal { value: f64, x: Vec<f64> },
+    Unbounded,
+}
+
+impl Solution {
+    /* Value rounded down to the integers the objective is made of */
+    pub fn bound(&self) -> u64 {
+        match self {
+            Solution::Optimal { value, .. } => (value + 1e-6).floor().max(0.0) as u64,
+            Solution::Unbounded => u64::MAX,
+        }
+    }
+}
+
+pub fn maximize(c: &[f64], a: &[Vec<f64>], b: &[f64]) -> Solution {
+    let n = c.len();
+    let m = a.len();
+    let width = n + m + 1;
+
+    /* row 0..m are constraints, row m is the objective (reduced costs, negated) */
+    let mut tableau = vec![vec![0.0; width]; m + 1];
+    for (i, row) in a.iter().enumerate() {
+        tableau[i][..n].copy_from_slice(row);
+        tableau[i][n + i] = 1.0;
+        tableau[i][width - 1] = b[i].max(0.0);
+    }
+    for j in 0..n {
+        tableau[m][j] = -c[j];
+    }
+    let mut basis: Vec<usize> = (n..n + m).collect();
+
+    /* Dantzig's rule, falling back to Bland's rule to get out of cycling */
+    let mut iterations = 0;
+    let bland_after = 50 * (n + m + 1);
+    loop {
+        iterations += 1;
+        let entering = if iterations < bland_after {
+            let (j, value) = tableau[m][..width - 1].iter().cloned().enumerate()
+                .fold((usize::MAX, -EPSILON), |best, (j, value)| if value < best.1 { (j, value) } else { best });
+            if value >= -EPSILON { None } else { Some(j) }
+        } else {
+            tableau[m][..width - 1].iter().position(|&value| value < -EPSILON)
+        };
+        let Some(entering) = entering else {
+            break;
+        };
+
+        let mut leaving = None;
+        let mut best_ratio = f64::INFINITY;
+        for i in 0..m {
+            let coefficient = tableau[i][entering];
+            if coefficient > EPSILON {
+                let ratio = tableau[i][width - 1] / coefficient;
+                if ratio < best_ratio - EPSILON
+                    || (ratio < best_ratio + EPSILON && leaving.is_none_or(|l: usize| basis[i] < basis[l]))
+                {
+                    best_ratio = ratio;
+                    leaving = Some(i);
+                }
+            }
+        }
+        let Some(leaving) = leaving else {
+            return Solution::Unbounded;
+        };
+
+        pivot(&mut tableau, leaving, entering);
+        basis[leaving] = entering;
+    }
+
+    let mut x = vec![0.0; n];
+    for (i, &variable) in basis.iter().enumerate() {
+        if variable < n {
+            x[variable] = tableau[i][width - 1];
+        }
+    }
+    Solution::Optimal { value: tableau[m][width - 1], x }
+}
+
+fn pivot(tableau: &mut [Vec<f64>], row: usize, column: usize) {
+    let pivot = tableau[row][column];
+    for value in tableau[row].iter_mut() {
+        *value /= pivot;
+    }
+    let pivot_row = tableau[row].clone();
+    for (i, other) in tableau.iter_mut().enumerate() {
+        if i == row {
+            continue;
+        }
+        let factor = other[column];
+        if factor.abs() > EPSILON {
+            for (value, pivot_value) in other.iter_mut().zip(&pivot_row) {
+                *value -= factor * pivot_value;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn known_optimum() {
+        /* max 3x + 2y, x + y <= 4, x + 3y <= 6, x <= 3: x = 3, y = 1 */
+        let solution = maximize(&[3.0, 2.0], &[vec![1.0, 1.0], vec![1.0, 3.0], vec![1.0, 0.0]], &[4.0, 6.0, 3.0]);
+        let Solution::Optimal { value, x } = &solution else {
+            panic!("{:?}", solution);
+        };
+        assert!((value - 11.0).abs() < 1e-6);
+        assert!((x[0] - 3.0).abs() < 1e-6 && (x[1] - 1.0).abs() < 1e-6);
+        assert_eq!(solution.bound(), 11);
+    }
+
+    #[test]
+    fn fractional_optimum_rounds_down() {
+        /* max x + y, 2x + 2y <= 5 */
+        let solution = maximize(&[1.0, 1.0], &[vec![2.0, 2.0]], &[5.0]);
+        assert_eq!(solution.bound(), 2);
+    }
+
+    #[test]
+    fn unbounded() {
+        /* max x, y - x <= 1 */
+        let solution = maximize(&[1.0, 0.0], &[vec![-1.0, 1.0]], &[1.0]);
+        assert!(matches!(solution, Solution::Unbounded));
+        assert_eq!(solution.bound(), u64::MAX);
+    }
+}
@@ -1,19 +1,22 @@
-#![allow(warnings)]
+#![allow(clippy::type_complexity, clippy::too_many_arguments)]
 use std::collections::HashMap;
-use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::Arc;
-use std::env;
 use clap::{Arg, ArgAction, Command};
 use std::path::PathBuf;
 use std::thread::JoinHandle;
 
+/* println! that goes to the dashboard while one is drawn */
+macro_rules! say {
+    () => { crate::progress::print(String::new()) };
+    ($($arg:tt)*) => { crate::progress::print(format!($($arg)*)) };
+}
+
 mod lexer;
-mod forbidden_name;
+mod tabu;
 mod parser;
 mod tokens;
 mod dijkstra;
 mod genetic;
-mod simmulated_annealing;
+mod simulated_annealing;
 mod delay;
 mod aco;
 mod gen_file;
@@ -21,6 +24,18 @@ mod stock_scores;
 mod a_star;
 mod ida_star;
 mod sgs;
+mod progress;
+mod simulator;
+mod lp;
+mod search;
+mod branch_and_bound;
+mod beam;
+mod mcts;
+mod ilp;
+mod polish;
+mod lns;
+mod cp;
+mod steady;
 
 #[derive(Debug, Clone)]
 pub struct Process {
@@ -35,12 +50,43 @@ pub struct Data {
     pub stocks: HashMap<String, u64>,
     pub processes: Vec<Process>,
     pub objectives: Vec<String>,
+    /// Cycle by which every run has to end, from `--horizon` or a `horizon:` line
+    pub horizon: Option<u64>,
+}
+
+pub struct Options {
+    pub file: String,
+    pub delay: u32,
+    pub algorithms: Vec<String>,
+    pub tui: bool,
+    pub json: bool,
+    pub beam_width: usize,
+    pub beam_eval: beam::Evaluation,
+    pub ga_population: usize,
+    pub ga_generations: u64,
+    pub sa_cooling: simulated_annealing::Cooling,
+    pub aco: aco::Settings,
+    pub sgs_scheme: sgs::Scheme,
+    pub sgs_rule: sgs::Rule,
+    pub sgs_noise: f64,
+    pub polish: bool,
+    pub lns_selection: lns::Selection,
+    pub horizon: Option<u64>,
+}
+
+pub enum Mode {
+    Run(Options),
+    ExportLp { file: String, output: String, format: ilp::Format, horizon: Option<u64>, bucket: Option<u64> },
+    ImportSol { file: String, solution: String, json: bool },
+    Steady { file: String, horizon: Option<u64>, json: bool },
 }
 
-fn get_args() -> (String, u32, Vec<String>) {
+fn get_args() -> Mode {
     let matches = Command::new("my_cli_app")
         .version("1.0")
         .about("Executes various algorithms on the provided file")
+        .args_conflicts_with_subcommands(true)
+        .subcommand_negates_reqs(true)
         .arg(
             Arg::new("file")
                 .help("Path to the file")
@@ -58,11 +104,254 @@ fn get_args() -> (String, u32, Vec<String>) {
                 .help("Algorithms to execute")
                 .required(false)
                 .action(ArgAction::Append)
-                .value_parser(["dijkstra", "aco", "tabu", "ga", "sa", "a*", "ida*", "sgs", "all"])
+                .value_parser(["dijkstra", "aco", "tabu", "ga", "sa", "a*", "ida*", "sgs", "bnb", "beam", "mcts", "lns", "cp", "all"])
                 .ignore_case(true),
         )
+        .arg(
+            Arg::new("tui")
+                .long("tui")
+                .help("Show a live dashboard of solver progress")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("json")
+                .long("json")
+                .help("Write the logs as JSON schedules instead of traces")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("beam-width")
+                .long("beam-width")
+                .help("Number of states kept per layer by the beam search")
+                .value_parser(clap::value_parser!(usize))
+                .default_value("64"),
+        )
+        .arg(
+            Arg::new("beam-eval")
+                .long("beam-eval")
+                .help("State evaluation used by the beam search")
+                .value_parser(["bound", "scores", "objective"])
+                .default_value("bound"),
+        )
+        .arg(
+            Arg::new("ga-population")
+                .long("ga-population")
+                .help("Individuals per generation of the genetic algorithm")
+                .value_parser(clap::value_parser!(usize))
+                .default_value("200"),
+        )
+        .arg(
+            Arg::new("ga-generations")
+                .long("ga-generations")
+                .help("Generations of the genetic algorithm (0: until the delay elapses)")
+                .value_parser(clap::value_parser!(u64))
+                .default_value("0"),
+        )
+        .arg(
+            Arg::new("sa-cooling")
+                .long("sa-cooling")
+                .help("Cooling schedule of the simulated annealing")
+                .value_parser(["geometric", "linear", "reheat"])
+                .default_value("geometric"),
+        )
+        .arg(
+            Arg::new("aco-ants")
+                .long("aco-ants")
+                .help("Ants built per iteration of the ant colony")
+                .value_parser(clap::value_parser!(usize))
+                .default_value("32"),
+        )
+        .arg(
+            Arg::new("aco-evaporation")
+                .long("aco-evaporation")
+                .help("Share of every pheromone trail evaporated per iteration")
+                .value_parser(clap::value_parser!(f64))
+                .default_value("0.05"),
+        )
+        .arg(
+            Arg::new("aco-alpha")
+                .long("aco-alpha")
+                .help("Weight of the pheromone in the ants' choices")
+                .value_parser(clap::value_parser!(f64))
+                .default_value("1"),
+        )
+        .arg(
+            Arg::new("aco-beta")
+                .long("aco-beta")
+                .help("Weight of the heuristic in the ants' choices")
+                .value_parser(clap::value_parser!(f64))
+                .default_value("2"),
+        )
+        .arg(
+            Arg::new("aco-update")
+                .long("aco-update")
+                .help("Ants laying pheromone: iteration and global best, or the best ranked ones")
+                .value_parser(["elitist", "rank"])
+                .default_value("elitist"),
+        )
+        .arg(
+            Arg::new("aco-context")
+                .long("aco-context")
+                .help("What pheromone trails are attached to besides the process")
+                .value_parser(["depth", "stocks"])
+                .default_value("depth"),
+        )
+        .arg(
+            Arg::new("sgs-scheme")
+                .long("sgs-scheme")
+                .help("Schedule generation scheme: run by run, or completion by completion")
+                .value_parser(["serial", "parallel"])
+                .default_value("parallel"),
+        )
+        .arg(
+            Arg::new("sgs-rule")
+                .long("sgs-rule")
+                .help("Priority rule of the schedule generation scheme")
+                .value_parser(["score", "shortest", "distance", "successors", "random"])
+                .default_value("score"),
+        )
+        .arg(
+            Arg::new("sgs-noise")
+                .long("sgs-noise")
+                .help("Relative noise on the SGS priorities after the first pass (0: a single deterministic pass)")
+                .value_parser(clap::value_parser!(f64))
+                .default_value("0.5"),
+        )
+        .arg(
+            Arg::new("lns-selection")
+                .long("lns-selection")
+                .help("How the large neighborhood search picks its destroy and repair operators")
+                .value_parser(["adaptive", "uniform"])
+                .default_value("adaptive"),
+        )
+        .arg(
+            Arg::new("horizon")
+                .long("horizon")
+                .help("Cycle by which every run has to end, overriding the file's horizon line")
+                .value_parser(clap::value_parser!(u64)),
+        )
+        .arg(
+            Arg::new("no-polish")
+                .long("no-polish")
+                .help("Write every result as its solver found it, without the local search pass")
+                .action(ArgAction::SetTrue),
+        )
+        .subcommand(
+            Command::new("export-lp")
+                .about("Writes the time-indexed MILP of the file for an external solver")
+                .arg(
+                    Arg::new("file")
+                        .help("Path to the file")
+                        .required(true)
+                        .value_parser(clap::value_parser!(PathBuf)),
+                )
+                .arg(
+                    Arg::new("output")
+                        .long("output")
+                        .short('o')
+                        .help("Where to write the model (default: logs/<name>.lp or .mps)"),
+                )
+                .arg(
+                    Arg::new("format")
+                        .long("format")
+                        .help("CPLEX LP or free MPS")
+                        .value_parser(["lp", "mps"])
+                        .default_value("lp"),
+                )
+                .arg(
+                    Arg::new("horizon")
+                        .long("horizon")
+                        .help("Cycle by which every run has to end (default: the file's horizon, else 10 times the longest process)")
+                        .value_parser(clap::value_parser!(u64)),
+                )
+                .arg(
+                    Arg::new("bucket")
+                        .long("bucket")
+                        .help("Cycles per time bucket (default: gcd of the process times)")
+                        .value_parser(clap::value_parser!(u64)),
+                ),
+        )
+        .subcommand(
+            Command::new("import-sol")
+                .about("Turns an external solution of the exported model into a validated log")
+                .arg(
+                    Arg::new("file")
+                        .help("Path to the file")
+                        .required(true)
+                        .value_parser(clap::value_parser!(PathBuf)),
+                )
+                .arg(
+                    Arg::new("solution")
+                        .help("Solution file with one variable=value per line")
+                        .required(true)
+                        .value_parser(clap::value_parser!(PathBuf)),
+                )
+                .arg(
+                    Arg::new("json")
+                        .long("json")
+                        .help("Write the log as a JSON schedule instead of a trace")
+                        .action(ArgAction::SetTrue),
+                ),
+        )
+        .subcommand(
+            Command::new("steady")
+                .about("Finds the repeating schedule with the best long-run objective rate and unrolls it")
+                .arg(
+                    Arg::new("file")
+                        .help("Path to the file")
+                        .required(true)
+                        .value_parser(clap::value_parser!(PathBuf)),
+                )
+                .arg(
+                    Arg::new("horizon")
+                        .long("horizon")
+                        .help("Cycle by which every run of the unrolled trace has to end (default: the file's horizon, else 10 periods)")
+                        .value_parser(clap::value_parser!(u64)),
+                )
+                .arg(
+                    Arg::new("json")
+                        .long("json")
+                        .help("Write the log as a JSON schedule instead of a trace")
+                        .action(ArgAction::SetTrue),
+                ),
+        )
         .get_matches();
 
+    if let Some(export) = matches.subcommand_matches("export-lp") {
+        let file = export.get_one::<PathBuf>("file").unwrap().clone();
+        let format = match export.get_one::<String>("format").unwrap().as_str() {
+            "mps" => ilp::Format::Mps,
+            _ => ilp::Format::Lp,
+        };
+        let output = export.get_one::<String>("output").cloned().unwrap_or_else(|| {
+            let name = file.file_stem().map(|stem| stem.to_string_lossy().to_string()).unwrap_or_else(|| "model".to_string());
+            format!("logs/{}.{}", name, if format == ilp::Format::Mps { "mps" } else { "lp" })
+        });
+        return Mode::ExportLp {
+            file: file.to_string_lossy().to_string(),
+            output,
+            format,
+            horizon: export.get_one::<u64>("horizon").cloned(),
+            bucket: export.get_one::<u64>("bucket").cloned(),
+        };
+    }
+
+    if let Some(steady) = matches.subcommand_matches("steady") {
+        return Mode::Steady {
+            file: steady.get_one::<PathBuf>("file").unwrap().to_string_lossy().to_string(),
+            horizon: steady.get_one::<u64>("horizon").cloned(),
+            json: steady.get_flag("json"),
+        };
+    }
+
+    if let Some(import) = matches.subcommand_matches("import-sol") {
+        return Mode::ImportSol {
+            file: import.get_one::<PathBuf>("file").unwrap().to_string_lossy().to_string(),
+            solution: import.get_one::<PathBuf>("solution").unwrap().to_string_lossy().to_string(),
+            json: import.get_flag("json"),
+        };
+    }
+
     let file: PathBuf = matches.get_one::<PathBuf>("file").unwrap().clone();
 
     let delay: u32 = *matches.get_one::<u32>("delay").unwrap_or(&3);
@@ -70,39 +359,295 @@ fn get_args() -> (String, u32, Vec<String>) {
     let algorithms: Vec<String> = matches
         .get_many::<String>("algorithms")
         .map(|vals| vals.map(|v| v.to_string()).collect())
-        .unwrap_or_else(Vec::new);
+        .unwrap_or_default();
+
+    let tui: bool = matches.get_flag("tui");
 
-    (file.to_string_lossy().to_string(), delay, algorithms)
+    let json: bool = matches.get_flag("json");
+
+    let beam_width: usize = (*matches.get_one::<usize>("beam-width").unwrap()).max(1);
+
+    let beam_eval = match matches.get_one::<String>("beam-eval").unwrap().as_str() {
+        "scores" => beam::Evaluation::StockScores,
+        "objective" => beam::Evaluation::Objective,
+        _ => beam::Evaluation::Bound,
+    };
+
+    let ga_population: usize = *matches.get_one::<usize>("ga-population").unwrap();
+
+    let ga_generations: u64 = *matches.get_one::<u64>("ga-generations").unwrap();
+
+    let sa_cooling = match matches.get_one::<String>("sa-cooling").unwrap().as_str() {
+        "linear" => simulated_annealing::Cooling::Linear,
+        "reheat" => simulated_annealing::Cooling::Reheating,
+        _ => simulated_annealing::Cooling::Geometric,
+    };
+
+    let aco = aco::Settings {
+        ants: *matches.get_one::<usize>("aco-ants").unwrap(),
+        evaporation: *matches.get_one::<f64>("aco-evaporation").unwrap(),
+        alpha: *matches.get_one::<f64>("aco-alpha").unwrap(),
+        beta: *matches.get_one::<f64>("aco-beta").unwrap(),
+        update: match matches.get_one::<String>("aco-update").unwrap().as_str() {
+            "rank" => aco::Update::Rank,
+            _ => aco::Update::Elitist,
+        },
+        context: match matches.get_one::<String>("aco-context").unwrap().as_str() {
+            "stocks" => aco::Context::Stocks,
+            _ => aco::Context::Depth,
+        },
+    };
+
+    let sgs_scheme = match matches.get_one::<String>("sgs-scheme").unwrap().as_str() {
+        "serial" => sgs::Scheme::Serial,
+        _ => sgs::Scheme::Parallel,
+    };
+
+    let sgs_rule = match matches.get_one::<String>("sgs-rule").unwrap().as_str() {
+        "shortest" => sgs::Rule::ShortestDelay,
+        "distance" => sgs::Rule::ObjectiveDistance,
+        "successors" => sgs::Rule::MostSuccessors,
+        "random" => sgs::Rule::Random,
+        _ => sgs::Rule::Score,
+    };
+
+    let sgs_noise: f64 = matches.get_one::<f64>("sgs-noise").unwrap().max(0.0);
+
+    let lns_selection = match matches.get_one::<String>("lns-selection").unwrap().as_str() {
+        "uniform" => lns::Selection::Uniform,
+        _ => lns::Selection::Adaptive,
+    };
+
+    let polish: bool = !matches.get_flag("no-polish");
+
+    let horizon: Option<u64> = matches.get_one::<u64>("horizon").cloned();
+
+    Mode::Run(Options {
+        file: file.to_string_lossy().to_string(),
+        delay,
+        algorithms,
+        tui,
+        json,
+        beam_width,
+        beam_eval,
+        ga_population,
+        ga_generations,
+        sa_cooling,
+        aco,
+        sgs_scheme,
+        sgs_rule,
+        sgs_noise,
+        polish,
+        lns_selection,
+        horizon,
+    })
 }
 
-fn main() {
+fn log_name(algorithm: &str, json: bool) -> String {
+    format!("logs/{}_log.{}", algorithm, if json { "json" } else { "txt" })
+}
 
-    let (file, delay, mut algorithms) = get_args();
-    let mut handles: Vec<JoinHandle<()>> = Vec::new();
+/* Every result is replayed by the simulator, then polished unless disabled;
+ * gen_file only writes what it accepts */
+fn write_result(
+    data: &Data,
+    algorithm: &str,
+    log: &simulator::Log,
+    claimed_time: u64,
+    claimed_stocks: &HashMap<String, u64>,
+    json: bool,
+    polish: Option<std::time::Duration>,
+    handles: &mut Vec<JoinHandle<()>>,
+    summary: &mut Vec<(String, simulator::Validated)>,
+) {
+    let mut validated = simulator::validate(data, log, claimed_time, claimed_stocks);
+    say!("Validation: {}\n", validated.validity.describe());
+    if let (Some(budget), false) = (polish, validated.validity == simulator::Validity::Rejected) {
+        let (log, outcome, moves) = polish::polish(data, &validated.log, budget);
+        say!("Polished: objective {} -> {}, makespan {} -> {} ({} moves)\n",
+            validated.outcome.objective, outcome.objective, validated.outcome.makespan, outcome.makespan, moves.total());
+        validated.log = log;
+        validated.outcome = outcome;
+    }
+    if validated.validity != simulator::Validity::Rejected {
+        handles.push(gen_file::run_in_thread(
+            log_name(algorithm, json),
+            validated.outcome.stocks.clone(),
+            validated.log.clone(),
+            validated.outcome.makespan,
+        ));
+    }
+    summary.push((algorithm.to_string(), validated));
+}
 
-    /* PARSING */
-    let file_static: &'static str = Box::leak(file.clone().into_boxed_str());
+fn load_data(file: &str) -> Option<Data> {
+    let file_static: &'static str = Box::leak(file.to_string().into_boxed_str());
 
     let mut parser = parser::Parser::new(file_static);
-    match parser.parse() {
-        Err(err) => {
-            eprintln!("\n\nFATAL ERROR !!!!!: {:?}", err);
-            return;
-        }
-        _ => {}
-    };
-    let x = Data {
+    if let Err(err) = parser.parse() {
+        eprintln!("\n\nFATAL ERROR !!!!!: {:?}", err);
+        return None;
+    }
+    Some(Data {
         stocks: parser.stocks,
         processes: parser.process,
         objectives: parser.optimize.unwrap(),
+        horizon: parser.horizon,
+    })
+}
+
+/* Data from a configuration written inline, through a temporary file since
+ * the parser reads files */
+#[cfg(test)]
+fn inline_data(content: &str) -> Data {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    static FILES: AtomicUsize = AtomicUsize::new(0);
+    let path = std::env::temp_dir().join(format!("krpsim_{}_{}", std::process::id(), FILES.fetch_add(1, Ordering::Relaxed)));
+    std::fs::write(&path, content).unwrap();
+    let data = load_data(&path.to_string_lossy());
+    std::fs::remove_file(&path).unwrap();
+    data.unwrap()
+}
+
+fn export_lp(file: &str, output: &str, format: ilp::Format, horizon: Option<u64>, bucket: Option<u64>) {
+    let Some(data) = load_data(file) else {
+        return;
+    };
+    let horizon = horizon.or(data.horizon).unwrap_or_else(|| ilp::default_horizon(&data));
+    let bucket = bucket.unwrap_or_else(|| ilp::default_bucket(&data));
+    let model = ilp::TimeIndexed::new(&data, horizon, bucket);
+
+    if let Some(parent) = std::path::Path::new(output).parent() {
+        let _ = std::fs::create_dir_all(parent);
+    }
+    let written = std::fs::File::create(output).and_then(|file| {
+        let mut out = std::io::BufWriter::new(file);
+        model.write(format, &mut out)
+    });
+    match written {
+        Ok(()) => say!("Model written to {} (horizon {}, bucket {})", output, model.horizon, model.bucket),
+        Err(e) => {
+            eprintln!("Error writing {}: {}", output, e);
+            return;
+        }
+    }
+    match model.relaxation_bound() {
+        Some(bound) => say!("LP relaxation bound on the objective: {}", bound),
+        None => say!("LP relaxation too large to solve here"),
+    }
+}
+
+fn import_sol(file: &str, solution: &str, json: bool) {
+    let Some(data) = load_data(file) else {
+        return;
+    };
+    let values = match std::fs::read_to_string(solution).map_err(|e| e.to_string()).and_then(|text| ilp::read_solution(&text)) {
+        Ok(values) => values,
+        Err(e) => {
+            eprintln!("Error reading {}: {}", solution, e);
+            return;
+        }
+    };
+
+    let (log, finish, claimed_stocks, warnings) = ilp::solution_log(&data, &values);
+    for warning in &warnings {
+        say!("Warning: {}", warning);
+    }
+    say!("Imported {} starts, finishing at {}", log.len(), finish);
+
+    /* without stock variables in the file there is no claim to check */
+    let claimed_stocks = claimed_stocks.unwrap_or_else(|| {
+        simulator::simulate(&data, &log).map(|outcome| outcome.stocks).unwrap_or_default()
+    });
+
+    let mut handles = Vec::new();
+    let mut summary = Vec::new();
+    write_result(&data, "import", &log, finish, &claimed_stocks, json, None, &mut handles, &mut summary);
+    for (_, validated) in &summary {
+        say!("objective {}  makespan {}", validated.outcome.objective, validated.outcome.makespan);
+    }
+    for handle in handles {
+        if let Err(e) = handle.join() {
+            eprintln!("Thread panicked: {:?}", e);
+        }
+    }
+}
+
+fn steady(file: &str, horizon: Option<u64>, json: bool) {
+    let Some(mut data) = load_data(file) else {
+        return;
+    };
+    let cycle = match steady::cycle(&data) {
+        Ok(cycle) => cycle,
+        Err(e) => {
+            say!("No steady state: {}", e);
+            return;
+        }
+    };
+    say!("Period: {} cycles", cycle.period);
+    say!("Cycle:");
+    for (id, count) in &cycle.counts {
+        say!("  {} x{}", id, count);
+    }
+    say!("Rate: {:.4e} objective per cycle (LP optimum {:.4e})", cycle.rate, cycle.lp_rate);
+
+    let horizon = horizon.or(data.horizon).unwrap_or(cycle.period.saturating_mul(10));
+    data.horizon = Some(horizon);
+    let (log, reached) = steady::unroll(&data, &cycle, horizon);
+    let Ok(outcome) = simulator::simulate(&data, &log) else {
+        eprintln!("Unrolled trace rejected by the simulator");
+        return;
+    };
+    if reached < horizon {
+        say!("Unrolling cut short at cycle {} of {}", reached, horizon);
+    }
+    say!("Unrolled up to cycle {}: {} starts, objective {}\n", reached, log.len(), outcome.objective);
+
+    let mut handles = Vec::new();
+    let mut summary = Vec::new();
+    write_result(&data, "steady", &log, outcome.makespan, &outcome.stocks, json, None, &mut handles, &mut summary);
+    for handle in handles {
+        if let Err(e) = handle.join() {
+            eprintln!("Thread panicked: {:?}", e);
+        }
+    }
+}
+
+fn main() {
+
+    let options = match get_args() {
+        Mode::ExportLp { file, output, format, horizon, bucket } => {
+            export_lp(&file, &output, format, horizon, bucket);
+            return;
+        }
+        Mode::ImportSol { file, solution, json } => {
+            import_sol(&file, &solution, json);
+            return;
+        }
+        Mode::Steady { file, horizon, json } => {
+            steady(&file, horizon, json);
+            return;
+        }
+        Mode::Run(options) => options,
+    };
+    let (file, delay, mut algorithms, tui, json) = (options.file.clone(), options.delay, options.algorithms.clone(), options.tui, options.json);
+    let mut handles: Vec<JoinHandle<()>> = Vec::new();
+
+    /* PARSING */
+    let Some(mut x) = load_data(&file) else {
+        return;
     };
-    println!("stocks: {:?}\n", x.stocks);
-    println!("processes:");
+    x.horizon = options.horizon.or(x.horizon);
+    say!("stocks: {:?}\n", x.stocks);
+    say!("processes:");
     for p in &x.processes {
-        println!("{:?}", p);
+        say!("{:?}", p);
+    }
+    say!();
+    say!("objectives: {:?}\n", x.objectives);
+    if let Some(horizon) = x.horizon {
+        say!("horizon: {}\n", horizon);
     }
-    println!();
-    println!("objectives: {:?}\n", x.objectives);
     /**********************/
 
     if algorithms.contains(&"all".to_string()) || algorithms.is_empty() {
@@ -115,94 +660,198 @@ fn main() {
             "a*".to_string(),
             "ida*".to_string(),
             "sgs".to_string(),
+            "bnb".to_string(),
+            "beam".to_string(),
+            "mcts".to_string(),
+            "lns".to_string(),
+            "cp".to_string(),
         ];
     }
 
+    let mut summary: Vec<(String, simulator::Validated)> = Vec::new();
+    let dashboard = if tui { Some(progress::Dashboard::start()) } else { None };
+    let budget = std::time::Duration::from_secs(delay as u64);
+    /* a tenth of the delay, but enough for a few sweeps on short runs */
+    let polish = options.polish.then(|| (budget / 10).max(std::time::Duration::from_millis(500)));
+
     for algorithm in algorithms {
+        let reporter = match &dashboard {
+            Some(dashboard) => dashboard.reporter(&algorithm.to_lowercase(), budget),
+            None => progress::Reporter::disabled(),
+        };
         match algorithm.to_lowercase().as_str() {
             "dijkstra" => {
                 /* DIJKSTRA ALGO */
-                println!("\x1b[36m\nOptimizing with Dijkstra's algorithm...\n\x1b[0m");
-                if let Some((time, final_stocks, best_log)) = dijkstra::optimize(x.clone(), delay) {
-                    println!("Optimized in {} units of time with stocks: {:?}\n", time, final_stocks);
-                    handles.push(gen_file::run_in_thread("logs/dijkstra_log.txt".to_string(), final_stocks.clone(), best_log.clone(), time));
+                say!("\x1b[36m\nOptimizing with Dijkstra's algorithm...\n\x1b[0m");
+                if let Some((time, final_stocks, best_log)) = dijkstra::optimize(x.clone(), delay, &reporter) {
+                    say!("Optimized in {} units of time with stocks: {:?}\n", time, final_stocks);
+                    write_result(&x, "dijkstra", &best_log, time, &final_stocks, json, polish, &mut handles, &mut summary);
                 } else {
-                    println!("No solution found");
+                    say!("No solution found");
                 }
                 /**********************/
             },
             "aco" => {
                 /* ACO ALGO */
-                println!("\x1b[36m\nOptimizing with Ant Colony Optimitzation ...\n\x1b[0m");
+                say!("\x1b[36m\nOptimizing with Ant Colony Optimitzation ...\n\x1b[0m");
 
-                let (best_solution, best_time, best_stocks, best_log) = aco::aco_optimization(&x, usize::MAX, 10000, delay);
-                println!("Optimized in {:?} units of time with stocks: {:?}\n", best_time, best_stocks);
-                handles.push(gen_file::run_in_thread("logs/aco_log.txt".to_string(),  best_stocks.clone(), best_log.clone(), best_time));
+                let Some((best_time, best_stocks, best_log)) = aco::aco_optimization(&x, usize::MAX, delay, &options.aco, &reporter) else {
+                    say!("No solution found");
+                    continue;
+                };
+                say!("Optimized in {:?} units of time with stocks: {:?}\n", best_time, best_stocks);
+                write_result(&x, "aco", &best_log, best_time, &best_stocks, json, polish, &mut handles, &mut summary);
                 /**********************/
             },
             "tabu" => {
                 /* TABU SEARCH ALGO */ 
-                println!("\x1b[36m\nOptimizing with Tabu Search...\n\x1b[0m");
+                say!("\x1b[36m\nOptimizing with Tabu Search...\n\x1b[0m");
 
-                let (best_solution, best_time, best_log) = forbidden_name::tabu_search(&x, usize::MAX, 1000, delay);
-                println!("Optimized in {} units of time with stocks: {:?}\n", best_time, best_solution.stocks);
-                handles.push(gen_file::run_in_thread("logs/tabu_log.txt".to_string(), best_solution.stocks.clone(), best_log.clone(), best_time));
+                let (best_stocks, best_time, best_log) = tabu::tabu_search(&x, usize::MAX, delay, &reporter);
+                say!("Optimized in {} units of time with stocks: {:?}\n", best_time, best_stocks);
+                write_result(&x, "tabu", &best_log, best_time, &best_stocks, json, polish, &mut handles, &mut summary);
                 /**********************/
             },
             "ga" => {
                 /* GENETIC ALGO */
-                println!("\x1b[36m\nOptimizing with Genetic Algorithm...\n\x1b[0m");
-                let Some((best_time, best_stocks, best_log)) = genetic::genetic_algorithm(x.clone(), delay) else {
-                    println!("No solution found");
+                say!("\x1b[36m\nOptimizing with Genetic Algorithm...\n\x1b[0m");
+                let Some((best_time, best_stocks, best_log)) = genetic::genetic_algorithm(x.clone(), delay, options.ga_population, options.ga_generations, &reporter) else {
+                    say!("No solution found");
                     continue;
                 };
-                println!("Optimized in {} units of time with stocks: {:?}\n", best_time, best_stocks);
-                handles.push(gen_file::run_in_thread("logs/genetic_log.txt".to_string(), best_stocks.clone(), best_log.clone(), best_time));
+                say!("Optimized in {} units of time with stocks: {:?}\n", best_time, best_stocks);
+                write_result(&x, "genetic", &best_log, best_time, &best_stocks, json, polish, &mut handles, &mut summary);
                 /**********************/
             },
             "sa" => {
                 /* SA_STAR ALGO */
-                println!("\x1b[36m\nOptimizing with Simmulated Annealing algorithm...\n\x1b[0m");
-                let (best_state, best_time, best_log) = simmulated_annealing::simulated_annealing(&x, 500000.0, 1.0, 0.98, delay as u64);
-                println!("Optimized in {} units of time with stocks: {:?}\n", best_time, best_state);
+                say!("\x1b[36m\nOptimizing with Simmulated Annealing algorithm...\n\x1b[0m");
+                let (best_state, best_time, best_log) = simulated_annealing::simulated_annealing(&x, options.sa_cooling, delay as u64, &reporter);
+                say!("Optimized in {} units of time with stocks: {:?}\n", best_time, best_state);
+                write_result(&x, "sa", &best_log, best_time, &best_state, json, polish, &mut handles, &mut summary);
                 /**********************/
 
             },
             "a*" => {
                 /* A_STAR ALGO */
-                println!("\x1b[36m\nOptimizing with A*'s algorithm...\n\x1b[0m");
-                if let Some((time, final_stocks, best_log)) = a_star::optimize(x.clone(), delay) {
-                    println!("Optimized in {} units of time with stocks: {:?}\n", time, final_stocks);
-                    handles.push(gen_file::run_in_thread("logs/a_star_log.txt".to_string(), final_stocks.clone(), best_log.clone(), time));
+                say!("\x1b[36m\nOptimizing with A*'s algorithm...\n\x1b[0m");
+                if let Some((time, final_stocks, best_log, proven)) = a_star::optimize(x.clone(), delay, &reporter) {
+                    say!("Optimized in {} units of time with stocks: {:?}", time, final_stocks);
+                    say!("{}\n", if proven { "Proven optimal" } else { "Not proven optimal (timer elapsed)" });
+                    write_result(&x, "a_star", &best_log, time, &final_stocks, json, polish, &mut handles, &mut summary);
                 } else {
-                    println!("No solution found");
+                    say!("No solution found");
                 }
                 /**********************/
             },
             "ida*" => {
                 /* IDA_STAR ALGO */
-                println!("\x1b[36m\nOptimizing with IDA*'s algorithm...\n\x1b[0m");
-                if let Some((time, final_stocks, best_log)) = ida_star::optimize(x.clone(), delay) {
-                    println!("Optimized in {} units of time with stocks: {:?}\n", time, final_stocks);
-                    handles.push(gen_file::run_in_thread("logs/ida_star_log.txt".to_string(), final_stocks.clone(), best_log.clone(), time));
+                say!("\x1b[36m\nOptimizing with IDA*'s algorithm...\n\x1b[0m");
+                if let Some((time, final_stocks, best_log, proven)) = ida_star::optimize(x.clone(), delay, &reporter) {
+                    say!("Optimized in {} units of time with stocks: {:?}", time, final_stocks);
+                    say!("{}\n", if proven { "Proven optimal" } else { "Not proven optimal (timer elapsed)" });
+                    write_result(&x, "ida_star", &best_log, time, &final_stocks, json, polish, &mut handles, &mut summary);
                 } else {
-                    println!("No solution found");
+                    say!("No solution found");
                 }
                 /**********************/
             },
             "sgs" => {
                 /* SA_STAR ALGO */
-                println!("\x1b[36m\nOptimizing with SGS algorithm...\n\x1b[0m");
-                let (data, time, log) = sgs::sgs_algorithm(x.clone(), delay as u64);
-                handles.push(gen_file::run_in_thread("logs/sgs_log.txt".to_string(), data.stocks.clone(), log.clone(), time));                
-                println!("Optimized in {} units of time with stocks: {:?}\n", time, data.stocks);
+                say!("\x1b[36m\nOptimizing with SGS algorithm...\n\x1b[0m");
+                let Some((time, final_stocks, log)) = sgs::sgs_algorithm(x.clone(), delay as u64, options.sgs_scheme, options.sgs_rule, options.sgs_noise, &reporter) else {
+                    say!("No solution found");
+                    continue;
+                };
+                write_result(&x, "sgs", &log, time, &final_stocks, json, polish, &mut handles, &mut summary);
+                say!("Optimized in {} units of time with stocks: {:?}\n", time, final_stocks);
                 /**********************/
 
             },
-            _ => println!("Unknown algorithm: {}", algorithm),
+            "bnb" => {
+                /* BRANCH AND BOUND ALGO */
+                say!("\x1b[36m\nOptimizing with Branch and Bound...\n\x1b[0m");
+                if let Some((time, final_stocks, best_log, proven)) = branch_and_bound::optimize(x.clone(), delay, &reporter) {
+                    say!("Optimized in {} units of time with stocks: {:?}", time, final_stocks);
+                    say!("{}\n", if proven { "Proven optimal" } else { "Not proven optimal (timer elapsed)" });
+                    write_result(&x, "bnb", &best_log, time, &final_stocks, json, polish, &mut handles, &mut summary);
+                } else {
+                    say!("No solution found");
+                }
+                /**********************/
+            },
+            "beam" => {
+                /* BEAM SEARCH ALGO */
+                say!("\x1b[36m\nOptimizing with Beam Search...\n\x1b[0m");
+                if let Some((time, final_stocks, best_log)) = beam::optimize(x.clone(), delay, options.beam_width, options.beam_eval, &reporter) {
+                    say!("Optimized in {} units of time with stocks: {:?}\n", time, final_stocks);
+                    write_result(&x, "beam", &best_log, time, &final_stocks, json, polish, &mut handles, &mut summary);
+                } else {
+                    say!("No solution found");
+                }
+                /**********************/
+            },
+            "mcts" => {
+                /* MONTE CARLO TREE SEARCH ALGO */
+                say!("\x1b[36m\nOptimizing with Monte Carlo Tree Search...\n\x1b[0m");
+                if let Some((time, final_stocks, best_log)) = mcts::optimize(x.clone(), delay, &reporter) {
+                    say!("Optimized in {} units of time with stocks: {:?}\n", time, final_stocks);
+                    write_result(&x, "mcts", &best_log, time, &final_stocks, json, polish, &mut handles, &mut summary);
+                } else {
+                    say!("No solution found");
+                }
+                /**********************/
+            },
+            "lns" => {
+                /* LARGE NEIGHBORHOOD SEARCH ALGO */
+                say!("\x1b[36m\nOptimizing with Large Neighborhood Search...\n\x1b[0m");
+                if let Some((time, final_stocks, best_log)) = lns::optimize(x.clone(), delay, options.lns_selection, &reporter) {
+                    say!("Optimized in {} units of time with stocks: {:?}\n", time, final_stocks);
+                    write_result(&x, "lns", &best_log, time, &final_stocks, json, polish, &mut handles, &mut summary);
+                } else {
+                    say!("No solution found");
+                }
+                /**********************/
+            },
+            "cp" => {
+                /* CONSTRAINT SEARCH ALGO */
+                say!("\x1b[36m\nOptimizing with Constraint Search...\n\x1b[0m");
+                let horizon = x.horizon.unwrap_or_else(|| ilp::default_horizon(&x));
+                if let Some((time, final_stocks, best_log, proven)) = cp::optimize(x.clone(), delay, horizon, &reporter) {
+                    say!("Optimized in {} units of time with stocks: {:?}", time, final_stocks);
+                    say!("{}\n", if proven { format!("Proven optimal by cycle {}", horizon) } else { "Not proven optimal (timer elapsed)".to_string() });
+                    write_result(&x, "cp", &best_log, time, &final_stocks, json, polish, &mut handles, &mut summary);
+                } else {
+                    say!("No solution found");
+                }
+                /**********************/
+            },
+            _ => say!("Unknown algorithm: {}", algorithm),
         }
     }
 
+    if let Some(dashboard) = dashboard {
+        dashboard.stop();
+    }
+
+    if !summary.is_empty() {
+        say!("\x1b[36m\nValidated results:\n\x1b[0m");
+        /* gap against the best objective any schedule could reach by the
+         * horizon, which only bounds the results when the solvers ran under it */
+        let bound = x.horizon.and_then(|horizon| ilp::objective_bound(&x, horizon));
+        for (algorithm, validated) in &summary {
+            let gap = match bound {
+                Some(bound) if bound > 0 && validated.outcome.objective <= bound => {
+                    format!("gap {:>6.2}%", 100.0 * (bound - validated.outcome.objective) as f64 / bound as f64)
+                }
+                _ => "gap      -".to_string(),
+            };
+            say!(
+                "{:<10} objective {:>10}  makespan {:>8}  {}  {}",
+                algorithm, validated.outcome.objective, validated.outcome.makespan, gap, validated.validity.describe()
+            );
+        }
+    }
 
     for handle in handles {
         if let Err(e) = handle.join() {
@@ -0,0 +1,152 @@
+use crate::Data;
+use crate::delay;
+use crate::ilp;
+use crate::sgs;
+use crate::progress::Reporter;
+use crate::search::{Model, Node};
+use rand::seq::SliceRandom;
+use std::collections::HashMap;
+use std::time::Instant;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering as AtomicOrdering};
+
+const EXPLORATION: f64 = std::f64::consts::SQRT_2;
+/* A tree node may have about WIDENING * sqrt(visits) children */
+const WIDENING: f64 = 2.0;
+/* Past this many tree nodes, iterations only roll out from existing leaves */
+const MAX_TREE: usize = 1_000_000;
+
+struct TreeNode {
+    state: Node,
+    parent: Option<usize>,
+    children: Vec<usize>,
+    /* starting sets not expanded yet, the next one to try last */
+    untried: Option<Vec<Vec<(usize, u64)>>>,
+    visits: f64,
+    reward: f64,
+}
+
+/* Default policy: a parallel SGS pass in greedy priority order from `node`,
+ * every eligible process starting as many times as it fits until nothing
+ * runs any more, time reaches `until` or the timer elapses */
+fn rollout(model: &Model, order: &[usize], node: &Node, until: u64, timer_flag: &Arc<AtomicBool>) -> Node {
+    sgs::parallel_pass(model, node.clone(), order, usize::MAX, until, |runs| runs, timer_flag)
+}
+
+/* Largest batches first, doing nothing second, the rest in random order */
+fn actions(model: &Model, node: &Node, timer_flag: &Arc<AtomicBool>) -> Vec<Vec<(usize, u64)>> {
+    let (mut combinations, _) = node.combinations(model, timer_flag);
+    if combinations.len() > 2 {
+        let wait = combinations.pop().unwrap();
+        let greedy = combinations.remove(0);
+        combinations.shuffle(&mut rand::thread_rng());
+        combinations.push(wait);
+        combinations.push(greedy);
+    } else {
+        combinations.reverse();
+    }
+    combinations
+}
+
+fn uct(tree: &[TreeNode], parent: usize, child: usize) -> f64 {
+    let node = &tree[child];
+    node.reward / node.visits + EXPLORATION * (tree[parent].visits.ln() / node.visits).sqrt()
+}
+
+/// Monte Carlo tree search (UCT) over the starting sets of every completion.
+/// Each iteration walks down the tree, adds one child under progressive
+/// widening, rolls it out with a greedy parallel SGS pass up to the horizon (ten times
+/// the longest process without one) and backs up the reached
+/// objective relative to the best one so far, starting from the greedy
+/// schedule of the root. Every rollout is a complete schedule, so the best of
+/// them is returned when the timer elapses.
+pub fn optimize(data: Data, delay: u32, progress: &Reporter) -> Option<(u64, HashMap<String, u64>, Vec<(String, u64, u64)>)> {
+    let model = Model::new(&data);
+    let timer_flag = delay::start_timer(std::time::Duration::from_secs(delay as u64));
+    let start = Instant::now();
+
+    let scores: Vec<i64> = data.processes.iter()
+        .map(|p| sgs::score_process(p, &data.stocks, &data.objectives))
+        .collect();
+    let mut order: Vec<usize> = (0..scores.len()).collect();
+    order.sort_by_key(|&process| -scores[process]);
+
+    /* processes without inputs would keep a rollout going forever */
+    let until = data.horizon.unwrap_or_else(|| ilp::default_horizon(&data));
+
+    /* the greedy schedule from the root is the incumbent to beat */
+    let root = Node::root(&model);
+    let mut best_node = rollout(&model, &order, &root, until, &timer_flag);
+    let mut best_objective = best_node.objective(&model);
+    let mut best_makespan = best_node.makespan;
+
+    let mut tree = vec![TreeNode { state: root, parent: None, children: Vec::new(), untried: None, visits: 0.0, reward: 0.0 }];
+    let mut iterations: u64 = 0;
+
+    while !timer_flag.load(AtomicOrdering::SeqCst) {
+        /* selection */
+        let mut current = 0;
+        loop {
+            if tree[current].untried.is_none() {
+                let untried = actions(&model, &tree[current].state, &timer_flag);
+                tree[current].untried = Some(untried);
+            }
+            let allowed = (WIDENING * tree[current].visits.sqrt()).ceil().max(1.0) as usize;
+            let can_widen = tree[current].children.len() < allowed
+                && tree.len() < MAX_TREE
+                && tree[current].untried.as_ref().is_some_and(|untried| !untried.is_empty());
+            if can_widen || tree[current].children.is_empty() {
+                break;
+            }
+            current = *tree[current].children.iter()
+                .max_by(|&&a, &&b| uct(&tree, current, a).partial_cmp(&uct(&tree, current, b)).unwrap_or(std::cmp::Ordering::Equal))
+                .unwrap();
+        }
+
+        /* expansion */
+        while tree.len() < MAX_TREE {
+            let Some(combination) = tree[current].untried.as_mut().and_then(|untried| untried.pop()) else {
+                break;
+            };
+            if let Some(state) = tree[current].state.child(&model, &combination) {
+                tree.push(TreeNode { state, parent: Some(current), children: Vec::new(), untried: None, visits: 0.0, reward: 0.0 });
+                let child = tree.len() - 1;
+                tree[current].children.push(child);
+                current = child;
+                break;
+            }
+        }
+
+        /* simulation */
+        let finished = rollout(&model, &order, &tree[current].state, until, &timer_flag);
+        /* a rollout cut short by the timer is a truncated schedule */
+        if timer_flag.load(AtomicOrdering::SeqCst) {
+            break;
+        }
+        let objective = finished.objective(&model);
+        if objective > best_objective || (objective == best_objective && finished.makespan < best_makespan) {
+            best_objective = objective;
+            best_makespan = finished.makespan;
+            best_node = finished;
+        }
+
+        /* backpropagation */
+        let reward = if best_objective == 0 { 0.0 } else { objective as f64 / best_objective as f64 };
+        let mut walk = Some(current);
+        while let Some(index) = walk {
+            tree[index].visits += 1.0;
+            tree[index].reward += reward;
+            walk = tree[index].parent;
+        }
+
+        iterations += 1;
+        progress.tick(iterations, best_objective, best_makespan, || format!("tree {}", tree.len()));
+    }
+
+    progress.finish(iterations, best_objective, best_makespan, format!("{} rollouts", iterations));
+
+    let elapsed = start.elapsed();
+    say!("MCTS executed in: {}.{:03} seconds\n", elapsed.as_secs(), elapsed.subsec_millis());
+
+    Some((best_node.makespan, model.stocks_map(&best_node.settled(&model)), best_node.log))
+}
@@ -12,15 +12,21 @@ pub struct Parser {
     pub stocks: HashMap<String, u64>,
     pub process: Vec<Process>,
     pub optimize: Option<Vec<String>>,
+    pub horizon: Option<u64>,
+    /* `horizon:<number>` lines, told apart from a stock named horizon once
+     * every process is known */
+    horizon_lines: Vec<u64>,
 }
 
 #[derive(Debug)]
+#[allow(dead_code)]
 pub enum Error {
     ExpectedLine,
     MissingOptimize,
     MissingStocks,
     MissingProcess,
     DuplicatedOptimize,
+    DuplicatedHorizon,
     DuplicatedIdentifier,
     UnexpectedEOF,
     UnexpectedToken(Token),
@@ -38,6 +44,8 @@ impl Parser {
                 stocks: HashMap::new(),
                 process: Vec::new(),
                 optimize: None,
+                horizon: None,
+                horizon_lines: Vec::new(),
             },
             Err(e) => {
                 eprintln!("Failed to lex file: {}", e);
@@ -58,6 +66,7 @@ impl Parser {
 
     pub fn parse(&mut self) -> Result<(), Error> {
         if self.current >= self.tokens.len() {
+            self.resolve_horizon()?;
             if self.optimize.is_none() {
                 return Err(Error::MissingOptimize);
             }
@@ -78,6 +87,7 @@ impl Parser {
             None => Err(Error::ExpectedLine),
             Some(t) => match t {
                 Token::Optimize => Ok(self.parse_optimize()?),
+                Token::Horizon => Ok(self.parse_horizon()?),
                 Token::Identifier(_) => {
                     let ident = self.consume_ident()?.to_string();
                     self.consume(Token::Colon)?;
@@ -97,7 +107,7 @@ impl Parser {
     }
 
     fn parse_process(&mut self, id: String) -> Result<(), Error> {
-        if self.stocks.get(&id).is_some() {
+        if self.stocks.contains_key(&id) {
             return Err(Error::DuplicatedIdentifier);
         }
         if self.process.iter().any(|x| x.id == id) {
@@ -124,12 +134,13 @@ impl Parser {
         }
         self.consume(Token::Colon)?;
         let time = self.consume_number()?;
-        Ok(self.process.push(Process {
+        self.process.push(Process {
             id,
             input,
             output,
             time,
-        }))
+        });
+        Ok(())
     }
 
     fn parse_tuple(&mut self) -> Result<Vec<(String, u64)>, Error> {
@@ -207,6 +218,35 @@ impl Parser {
         Ok(())
     }
 
+    /* A `horizon:<number>` line reads the same as the stock line of an item
+     * named horizon: the first one is that stock as soon as a process or the
+     * objective uses the item, and at most one is left for the horizon */
+    fn resolve_horizon(&mut self) -> Result<(), Error> {
+        let used = |item: &String| item == "horizon";
+        let is_item = self.process.iter().any(|p| p.input.iter().chain(&p.output).any(|(item, _)| used(item)))
+            || self.optimize.as_ref().is_some_and(|optimize| optimize.iter().any(used));
+        let mut lines = std::mem::take(&mut self.horizon_lines).into_iter();
+        if is_item {
+            if let Some(qty) = lines.next() {
+                self.stocks.insert("horizon".to_string(), qty);
+            }
+        }
+        self.horizon = lines.next();
+        if lines.next().is_some() {
+            return Err(Error::DuplicatedHorizon);
+        }
+        Ok(())
+    }
+
+    fn parse_horizon(&mut self) -> Result<(), Error> {
+        self.advance();
+        self.consume(Token::Colon)?;
+        let n = self.consume_number()?;
+        self.consume(Token::NewLine)?;
+        self.horizon_lines.push(n);
+        Ok(())
+    }
+
     fn consume(&mut self, token: Token) -> Result<(), Error> {
         match self.peek() {
             Some(t) if *t == token => {
@@ -260,3 +300,49 @@ impl Parser {
         self.tokens.get(self.current)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(name: &str, content: &str) -> Result<Parser, Error> {
+        let path = std::env::temp_dir().join(format!("krpsim_parser_{}_{}", std::process::id(), name));
+        std::fs::write(&path, content).unwrap();
+        let file: &'static str = Box::leak(path.to_string_lossy().to_string().into_boxed_str());
+        let mut parser = Parser::new(file);
+        let parsed = parser.parse();
+        std::fs::remove_file(&path).unwrap();
+        parsed.map(|_| parser)
+    }
+
+    #[test]
+    fn horizon_line() {
+        let parser = parse("line", "a:2\nuse:(a:1):(b:1):5\nhorizon:25\noptimize:(b)\n").unwrap();
+        assert_eq!(parser.horizon, Some(25));
+        assert!(!parser.stocks.contains_key("horizon"));
+    }
+
+    #[test]
+    fn horizon_as_a_stock() {
+        let steak = std::fs::read_to_string("resources/steak").unwrap().replace("poele", "horizon");
+        let parser = parse("stock", &steak).unwrap();
+        assert_eq!(parser.horizon, None);
+        assert_eq!(parser.stocks.get("horizon"), Some(&1));
+
+        let parser = parse("both", &format!("{}horizon:25\n", steak)).unwrap();
+        assert_eq!(parser.horizon, Some(25));
+        assert_eq!(parser.stocks.get("horizon"), Some(&1));
+    }
+
+    #[test]
+    fn horizon_as_a_process() {
+        let parser = parse("process", "a:2\nhorizon:(a:1):(b:1):5\noptimize:(b)\n").unwrap();
+        assert_eq!(parser.horizon, None);
+        assert_eq!(parser.process[0].id, "horizon");
+    }
+
+    #[test]
+    fn duplicated_horizon() {
+        assert!(matches!(parse("twice", "a:2\nuse:(a:1):(b:1):5\nhorizon:25\nhorizon:30\noptimize:(b)\n"), Err(Error::DuplicatedHorizon)));
+    }
+}
@@ -0,0 +1,227 @@
+use crate::Data;
+use crate::delay;
+use crate::simulator::{self, event_times, Log, Outcome};
+use std::cmp::Reverse;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering as AtomicOrdering};
+use std::time::{Duration, Instant};
+
+/* Objective first, then makespan, then fewer runs, then earlier starts */
+type Score = (u64, Reverse<u64>, Reverse<u64>, Reverse<u64>);
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Moves {
+    pub shifted: u64,
+    pub merged: u64,
+    pub dropped: u64,
+    pub added: u64,
+}
+
+impl Moves {
+    pub fn total(&self) -> u64 {
+        self.shifted + self.merged + self.dropped + self.added
+    }
+}
+
+fn score(log: &Log, outcome: &Outcome) -> Score {
+    let runs = log.iter().map(|(_, count, _)| count).sum();
+    let starts = log.iter().map(|(_, count, start)| count * start).sum();
+    (outcome.objective, Reverse(outcome.makespan), Reverse(runs), Reverse(starts))
+}
+
+/* Only logs the simulator accepts as they are count as moves */
+fn replay(data: &Data, mut log: Log) -> Option<(Log, Outcome)> {
+    log.sort_by_key(|(_, _, start)| *start);
+    simulator::simulate(data, &log).ok().map(|outcome| (log, outcome))
+}
+
+/* Entries of the same process at the same time become one */
+fn normalize(log: &Log) -> Log {
+    let mut merged: Log = Vec::with_capacity(log.len());
+    for (id, count, start) in log {
+        match merged.iter_mut().rev().take_while(|(_, _, time)| time == start).find(|(other, _, _)| other == id) {
+            Some(entry) => entry.1 += count,
+            None => merged.push((id.clone(), *count, *start)),
+        }
+    }
+    merged
+}
+
+struct Climb<'a> {
+    data: &'a Data,
+    log: Log,
+    outcome: Outcome,
+    score: Score,
+    moves: Moves,
+    timer_flag: Arc<AtomicBool>,
+}
+
+impl<'a> Climb<'a> {
+    fn elapsed(&self) -> bool {
+        self.timer_flag.load(AtomicOrdering::SeqCst)
+    }
+
+    /* First improvement: the candidate replaces the schedule if it is valid and better */
+    fn try_log(&mut self, candidate: Log) -> bool {
+        let Some((log, outcome)) = replay(self.data, candidate) else {
+            return false;
+        };
+        let candidate_score = score(&log, &outcome);
+        if candidate_score <= self.score {
+            return false;
+        }
+        self.log = log;
+        self.outcome = outcome;
+        self.score = candidate_score;
+        true
+    }
+
+    /// Each entry moves to the earliest event time where the schedule still runs.
+    fn shift_earlier(&mut self) -> bool {
+        let mut improved = false;
+        let mut entry = 0;
+        while entry < self.log.len() && !self.elapsed() {
+            let times = event_times(self.data, &self.log);
+            let start = self.log[entry].2;
+            for &time in times.iter().take_while(|&&time| time < start) {
+                let mut candidate = self.log.clone();
+                candidate[entry].2 = time;
+                if self.try_log(candidate) {
+                    self.moves.shifted += 1;
+                    improved = true;
+                    break;
+                }
+            }
+            /* the shifted entry moved before this index; later sweeps catch what this skips */
+            entry += 1;
+        }
+        improved
+    }
+
+    /// Later batches of a process join an earlier one when the stocks allow it.
+    fn merge_batches(&mut self) -> bool {
+        let mut improved = false;
+        let mut first = 0;
+        while first < self.log.len() && !self.elapsed() {
+            let mut second = first + 1;
+            while second < self.log.len() && !self.elapsed() {
+                if self.log[second].0 == self.log[first].0 {
+                    let mut candidate = self.log.clone();
+                    let (_, count, _) = candidate.remove(second);
+                    candidate[first].1 += count;
+                    if self.try_log(candidate) {
+                        self.moves.merged += 1;
+                        improved = true;
+                        continue;
+                    }
+                }
+                second += 1;
+            }
+            first += 1;
+        }
+        improved
+    }
+
+    /// Runs whose removal costs nothing go, from the last entry back: the
+    /// whole entry if possible, else as large a part of it as possible.
+    fn drop_useless(&mut self) -> bool {
+        let mut improved = false;
+        let mut entry = self.log.len();
+        while entry > 0 && !self.elapsed() {
+            entry -= 1;
+            if entry >= self.log.len() {
+                continue;
+            }
+            let mut candidate = self.log.clone();
+            candidate.remove(entry);
+            if self.try_log(candidate) {
+                self.moves.dropped += 1;
+                improved = true;
+                continue;
+            }
+            /* halving cuts, each tried again as long as it works */
+            let mut cut = self.log[entry].1 / 2;
+            while cut > 0 && !self.elapsed() {
+                let count = self.log[entry].1;
+                if cut >= count {
+                    cut = count / 2;
+                    continue;
+                }
+                let mut candidate = self.log.clone();
+                candidate[entry].1 -= cut;
+                if self.try_log(candidate) {
+                    self.moves.dropped += 1;
+                    improved = true;
+                } else {
+                    cut /= 2;
+                }
+            }
+        }
+        improved
+    }
+
+    /// Processes that the final stocks can still feed are started, as many
+    /// times as the leftovers allow, at the earliest event time that works.
+    fn add_leftover_runs(&mut self) -> bool {
+        let mut improved = false;
+        for process in &self.data.processes {
+            if self.elapsed() {
+                break;
+            }
+            let runs = process.input.iter()
+                .map(|(item, qty)| self.outcome.stocks.get(item).unwrap_or(&0) / qty)
+                .min()
+                .unwrap_or(0);
+            if runs == 0 {
+                continue;
+            }
+            for time in event_times(self.data, &self.log) {
+                let mut candidate = self.log.clone();
+                candidate.push((process.id.clone(), runs, time));
+                if self.try_log(candidate) {
+                    self.moves.added += 1;
+                    improved = true;
+                    break;
+                }
+            }
+        }
+        improved
+    }
+}
+
+/// Hill climbing on a feasible schedule: shifts runs earlier, merges batches,
+/// drops runs that bring nothing and adds runs fed by leftover stock, keeping
+/// a move only when the simulator accepts the result and it is better. Stops
+/// at a local optimum or when `budget` elapses.
+pub fn polish(data: &Data, log: &Log, budget: Duration) -> (Log, Outcome, Moves) {
+    let start = Instant::now();
+    /* merging can starve a run started in between, then the log stays as it
+     * is; a log the simulator rejects comes back repaired, with its outcome */
+    let Some((log, outcome)) = replay(data, normalize(log)).or_else(|| replay(data, log.clone())) else {
+        let (log, outcome, _, _) = simulator::repair(data, log);
+        return (log, outcome, Moves::default());
+    };
+
+    let mut climb = Climb {
+        data,
+        score: score(&log, &outcome),
+        log,
+        outcome,
+        moves: Moves::default(),
+        timer_flag: delay::start_timer(budget),
+    };
+    loop {
+        let mut improved = climb.add_leftover_runs();
+        improved |= climb.drop_useless();
+        improved |= climb.merge_batches();
+        improved |= climb.shift_earlier();
+        if !improved || climb.elapsed() {
+            break;
+        }
+    }
+
+    let elapsed = start.elapsed();
+    say!("Polish executed in: {}.{:03} seconds", elapsed.as_secs(), elapsed.subsec_millis());
+
+    (climb.log, climb.outcome, climb.moves)
+}
@@ -0,0 +1,208 @@
+use std::cell::Cell;
+use std::io::{self, Write};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::Mutex;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+const REFRESH: Duration = Duration::from_millis(250);
+/* Latest solver output lines shown under the table */
+const MESSAGES: usize = 12;
+
+/* Set while a dashboard is drawn: solver output then goes to it */
+static CONSOLE: Mutex<Option<Sender<Message>>> = Mutex::new(None);
+
+enum Message {
+    Progress(Update),
+    Line(String),
+}
+
+/// Prints solver output, or hands it to the dashboard while one is drawn:
+/// every redraw clears the screen, so output printed directly would be lost.
+pub fn print(text: String) {
+    match CONSOLE.lock().unwrap().as_ref() {
+        Some(sender) => {
+            for line in text.lines() {
+                let _ = sender.send(Message::Line(line.to_string()));
+            }
+        }
+        None => println!("{}", text),
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Update {
+    pub solver: String,
+    pub iterations: u64,
+    pub best_objective: u64,
+    pub best_makespan: u64,
+    pub detail: String,
+    pub elapsed: Duration,
+    pub budget: Duration,
+    pub finished: bool,
+}
+
+/// Handle a solver uses to publish its progress. A disabled reporter drops
+/// every update, so solvers can report unconditionally.
+pub struct Reporter {
+    solver: String,
+    budget: Duration,
+    start: Instant,
+    last_sent: Cell<Option<Instant>>,
+    sender: Option<Sender<Message>>,
+}
+
+impl Reporter {
+    pub fn disabled() -> Self {
+        Reporter {
+            solver: String::new(),
+            budget: Duration::ZERO,
+            start: Instant::now(),
+            last_sent: Cell::new(None),
+            sender: None,
+        }
+    }
+
+    /* Throttled: the detail closure only runs when an update is actually sent */
+    pub fn tick<F: FnOnce() -> String>(&self, iterations: u64, best_objective: u64, best_makespan: u64, detail: F) {
+        let Some(sender) = &self.sender else {
+            return;
+        };
+        let now = Instant::now();
+        if let Some(last) = self.last_sent.get() {
+            if now.duration_since(last) < REFRESH / 2 {
+                return;
+            }
+        }
+        self.last_sent.set(Some(now));
+        let _ = sender.send(Message::Progress(self.update(iterations, best_objective, best_makespan, detail(), false)));
+    }
+
+    pub fn finish(&self, iterations: u64, best_objective: u64, best_makespan: u64, detail: String) {
+        if let Some(sender) = &self.sender {
+            let _ = sender.send(Message::Progress(self.update(iterations, best_objective, best_makespan, detail, true)));
+        }
+    }
+
+    fn update(&self, iterations: u64, best_objective: u64, best_makespan: u64, detail: String, finished: bool) -> Update {
+        Update {
+            solver: self.solver.clone(),
+            iterations,
+            best_objective,
+            best_makespan,
+            detail,
+            elapsed: self.start.elapsed(),
+            budget: self.budget,
+            finished,
+        }
+    }
+}
+
+/// Live terminal view of the solvers run so far, one row each: solvers run
+/// one after another, so only the last row is live and the others show where
+/// their solver finished. Drawn on the alternate screen, with the latest
+/// solver output printed through `print` below the table; all of it is
+/// printed on the normal screen once the dashboard stops.
+pub struct Dashboard {
+    sender: Option<Sender<Message>>,
+    handle: Option<JoinHandle<(Vec<Update>, Vec<String>)>>,
+}
+
+impl Dashboard {
+    pub fn start() -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let handle = thread::spawn(move || render_loop(receiver));
+        *CONSOLE.lock().unwrap() = Some(sender.clone());
+        Dashboard { sender: Some(sender), handle: Some(handle) }
+    }
+
+    pub fn reporter(&self, solver: &str, budget: Duration) -> Reporter {
+        Reporter {
+            solver: solver.to_string(),
+            budget,
+            start: Instant::now(),
+            last_sent: Cell::new(None),
+            sender: self.sender.clone(),
+        }
+    }
+
+    /* Leaves the alternate screen and prints the solver output and the last
+     * frame on the normal one */
+    pub fn stop(mut self) {
+        CONSOLE.lock().unwrap().take();
+        self.sender.take();
+        if let Some(handle) = self.handle.take() {
+            if let Ok((rows, lines)) = handle.join() {
+                for line in &lines {
+                    println!("{}", line);
+                }
+                print!("{}", render(&rows, &[]));
+            }
+        }
+    }
+}
+
+fn render_loop(receiver: Receiver<Message>) -> (Vec<Update>, Vec<String>) {
+    let mut rows: Vec<Update> = Vec::new();
+    let mut lines: Vec<String> = Vec::new();
+    let mut stdout = io::stdout();
+    let _ = write!(stdout, "\x1b[?1049h\x1b[?25l");
+    let mut last_draw = Instant::now() - REFRESH;
+
+    loop {
+        match receiver.recv_timeout(REFRESH) {
+            Ok(Message::Progress(update)) => {
+                match rows.iter_mut().find(|row| row.solver == update.solver) {
+                    Some(row) => *row = update,
+                    None => rows.push(update),
+                }
+            }
+            Ok(Message::Line(line)) => lines.push(line),
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+        if last_draw.elapsed() >= REFRESH {
+            let _ = write!(stdout, "\x1b[2J\x1b[H{}", render(&rows, &lines[lines.len().saturating_sub(MESSAGES)..]));
+            let _ = stdout.flush();
+            last_draw = Instant::now();
+        }
+    }
+
+    let _ = write!(stdout, "\x1b[?25h\x1b[?1049l");
+    let _ = stdout.flush();
+    (rows, lines)
+}
+
+fn render(rows: &[Update], lines: &[String]) -> String {
+    let mut out = String::new();
+    out.push_str("\x1b[36mkrpsim solvers\x1b[0m\n\n");
+    out.push_str(&format!(
+        "{:<10} {:>12} {:>10} {:>12} {:>9} {:>9}  {}\n",
+        "solver", "objective", "makespan", "iter/s", "elapsed", "left", "detail"
+    ));
+    for row in rows {
+        let secs = row.elapsed.as_secs_f64();
+        let rate = if secs > 0.0 { row.iterations as f64 / secs } else { 0.0 };
+        let left = row.budget.saturating_sub(row.elapsed);
+        let state = if row.finished { "\x1b[32mdone\x1b[0m " } else { "" };
+        out.push_str(&format!(
+            "{:<10} {:>12} {:>10} {:>12.1} {:>8.1}s {:>8.1}s  {}{}\n",
+            row.solver,
+            row.best_objective,
+            row.best_makespan,
+            rate,
+            secs,
+            left.as_secs_f64(),
+            state,
+            row.detail
+        ));
+    }
+    if !lines.is_empty() {
+        out.push('\n');
+        for line in lines {
+            out.push_str(line);
+            out.push('\n');
+        }
+    }
+    out
+}
@@ -0,0 +1,434 @@
+use crate::Data;
+use crate::lp;
+use rand::Rng;
+use rand::seq::SliceRandom;
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, Ordering as AtomicOrdering};
+use std::sync::Arc;
+
+/* Past this many starting sets at one node the enumeration stops, and the
+ * search using it can no longer claim to be exhaustive */
+pub const MAX_COMBINATIONS: usize = 20_000;
+
+/* Past this many remembered states, the exact searches only check dominance
+ * against the ones kept */
+pub const MAX_VISITED: usize = 4_000_000;
+
+/* Value lost by a stock for each production step it is away from an objective */
+const DISCOUNT: f64 = 0.9;
+
+/* Index-based copy of `Data` for the exact searches, which clone and hash
+ * millions of stock vectors */
+
+#[derive(Debug, Clone)]
+pub struct Step {
+    pub id: String,
+    pub input: Vec<(usize, u64)>,
+    pub output: Vec<(usize, u64)>,
+    pub time: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct Model {
+    pub names: Vec<String>,
+    pub initial: Vec<u64>,
+    pub processes: Vec<Step>,
+    pub objectives: Vec<usize>,
+    /// Cycle by which every run has to end, `u64::MAX` without a horizon
+    pub horizon: u64,
+}
+
+impl Model {
+    pub fn new(data: &Data) -> Self {
+        let mut names: Vec<String> = data.stocks.keys().cloned().collect();
+        for process in &data.processes {
+            for (item, _) in process.input.iter().chain(process.output.iter()) {
+                if !names.contains(item) {
+                    names.push(item.clone());
+                }
+            }
+        }
+        names.sort();
+        let index = |item: &String| names.iter().position(|name| name == item).unwrap();
+
+        let processes = data.processes.iter().map(|process| Step {
+            id: process.id.clone(),
+            input: process.input.iter().map(|(item, qty)| (index(item), *qty)).collect(),
+            output: process.output.iter().map(|(item, qty)| (index(item), *qty)).collect(),
+            time: process.time,
+        }).collect();
+
+        Model {
+            initial: names.iter().map(|name| *data.stocks.get(name).unwrap_or(&0)).collect(),
+            objectives: data.objectives.iter().filter_map(|obj| names.iter().position(|name| name == obj)).collect(),
+            processes,
+            names,
+            horizon: data.horizon.unwrap_or(u64::MAX),
+        }
+    }
+
+    pub fn stocks_map(&self, stocks: &[u64]) -> HashMap<String, u64> {
+        self.names.iter().cloned().zip(stocks.iter().cloned()).collect()
+    }
+
+    pub fn objective(&self, stocks: &[u64]) -> u64 {
+        self.objectives.iter().map(|&obj| stocks[obj]).sum()
+    }
+
+    /// Whether a run of `process` started at `start` ends by the horizon.
+    pub fn ends_in_time(&self, process: usize, start: u64) -> bool {
+        start.saturating_add(self.processes[process].time) <= self.horizon
+    }
+
+    pub fn max_runs(&self, process: usize, stocks: &[u64]) -> u64 {
+        self.processes[process].input.iter()
+            .map(|&(item, qty)| stocks[item] / qty)
+            .min()
+            .unwrap_or(u64::MAX)
+    }
+
+    /// Value of one unit of each stock, in objective units: an objective is
+    /// worth 1, and an input is worth what its best consumer produces per unit
+    /// of input, discounted by each production step like the distance ranks of
+    /// `stock_scores`.
+    pub fn values(&self) -> Vec<f64> {
+        let mut values = vec![0.0; self.names.len()];
+        for &item in &self.objectives {
+            values[item] = 1.0;
+        }
+        for _ in 0..=self.names.len() {
+            for step in &self.processes {
+                let produced: f64 = step.output.iter().map(|&(item, qty)| qty as f64 * values[item]).sum();
+                let consumed: f64 = step.input.iter().map(|&(_, qty)| qty as f64).sum();
+                if consumed == 0.0 {
+                    continue;
+                }
+                for &(item, _) in &step.input {
+                    if !self.objectives.contains(&item) {
+                        values[item] = f64::max(values[item], DISCOUNT * produced / consumed);
+                    }
+                }
+            }
+        }
+        values
+    }
+
+    /// Optimistic objective reachable from `stocks` when every process may run
+    /// a fractional number of times and time is free: the LP relaxation of the
+    /// production counts. `u64::MAX` when production can grow without limit.
+    pub fn upper_bound(&self, stocks: &[u64]) -> u64 {
+        self.upper_bound_with(stocks, &vec![true; self.processes.len()])
+    }
+
+    /// Same relaxation, with only the `allowed` processes.
+    pub fn upper_bound_with(&self, stocks: &[u64], allowed: &[bool]) -> u64 {
+        let n = self.processes.len();
+        let mut c = vec![0.0; n];
+        let mut rows: Vec<Vec<f64>> = Vec::new();
+        let mut limits: Vec<f64> = Vec::new();
+
+        for (item, &available) in stocks.iter().enumerate() {
+            let mut row = vec![0.0; n];
+            for (p, process) in self.processes.iter().enumerate().filter(|&(p, _)| allowed[p]) {
+                for &(input, qty) in &process.input {
+                    if input == item {
+                        row[p] += qty as f64;
+                    }
+                }
+                for &(output, qty) in &process.output {
+                    if output == item {
+                        row[p] -= qty as f64;
+                    }
+                }
+            }
+            if self.objectives.contains(&item) {
+                for p in 0..n {
+                    c[p] -= row[p];
+                }
+            }
+            if row.iter().any(|&coefficient| coefficient > 0.0) {
+                rows.push(row);
+                limits.push(available as f64);
+            }
+        }
+
+        match lp::maximize(&c, &rows, &limits).bound() {
+            u64::MAX => u64::MAX,
+            gain => self.objective(stocks).saturating_add(gain),
+        }
+    }
+}
+
+/// A point of the concurrent schedule: runs started so far are either done
+/// (their outputs are in `stocks`) or in `running` as `(end, process, count)`.
+#[derive(Debug, Clone)]
+pub struct Node {
+    pub time: u64,
+    pub stocks: Vec<u64>,
+    pub running: Vec<(u64, usize, u64)>,
+    pub makespan: u64,
+    pub log: Vec<(String, u64, u64)>,
+}
+
+impl Node {
+    pub fn root(model: &Model) -> Self {
+        Node { time: 0, stocks: model.initial.clone(), running: Vec::new(), makespan: 0, log: Vec::new() }
+    }
+
+    /* Identifies the state regardless of the absolute time it was reached at */
+    pub fn key(&self) -> (Vec<u64>, Vec<(u64, usize, u64)>) {
+        let running = self.running.iter().map(|&(end, p, count)| (end - self.time, p, count)).collect();
+        (self.stocks.clone(), running)
+    }
+
+    /// Stocks once every running process has completed.
+    pub fn settled(&self, model: &Model) -> Vec<u64> {
+        let mut stocks = self.stocks.clone();
+        for &(_, p, count) in &self.running {
+            for &(item, qty) in &model.processes[p].output {
+                stocks[item] += qty * count;
+            }
+        }
+        stocks
+    }
+
+    /// Lower bound on the time left before the final makespan. Running processes
+    /// have to complete, and if the objective can still grow, some process with a
+    /// net objective gain has to run, after every input it lacks has been produced.
+    /// Production delays come from the production graph, relaxed so that a stock
+    /// is either already there in the settled stocks or costs its fastest producer.
+    pub fn remaining_time(&self, model: &Model, settled: &[u64], bound: u64) -> u64 {
+        let running = self.makespan.saturating_sub(self.time);
+        if bound != u64::MAX && model.objective(settled) >= bound {
+            return running;
+        }
+
+        let mut earliest = vec![u64::MAX; model.names.len()];
+        let process_delay = |process: usize, earliest: &Vec<u64>| -> u64 {
+            let step = &model.processes[process];
+            let mut ready = 0;
+            for &(item, qty) in &step.input {
+                if settled[item] < qty {
+                    ready = ready.max(earliest[item]);
+                }
+            }
+            ready.saturating_add(step.time)
+        };
+
+        /* Bellman-Ford style relaxation; the graph is tiny */
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for process in 0..model.processes.len() {
+                let done = process_delay(process, &earliest);
+                if done == u64::MAX {
+                    continue;
+                }
+                for &(item, _) in &model.processes[process].output {
+                    if done < earliest[item] {
+                        earliest[item] = done;
+                        changed = true;
+                    }
+                }
+            }
+        }
+
+        let gain = (0..model.processes.len())
+            .filter(|&process| {
+                let step = &model.processes[process];
+                let produced: u64 = step.output.iter().filter(|(item, _)| model.objectives.contains(item)).map(|(_, qty)| qty).sum();
+                let consumed: u64 = step.input.iter().filter(|(item, _)| model.objectives.contains(item)).map(|(_, qty)| qty).sum();
+                produced > consumed
+            })
+            .map(|process| process_delay(process, &earliest))
+            .min()
+            .unwrap_or(0);
+
+        running.max(if gain == u64::MAX { 0 } else { gain })
+    }
+
+    /// Runs of `process` that can start now: none if they would end after the
+    /// horizon, `u64::MAX` for a process without inputs.
+    pub fn max_runs(&self, model: &Model, process: usize) -> u64 {
+        if model.ends_in_time(process, self.time) { model.max_runs(process, &self.stocks) } else { 0 }
+    }
+
+    /// Objective reached if nothing else is started.
+    pub fn objective(&self, model: &Model) -> u64 {
+        model.objective(&self.settled(model))
+    }
+
+    pub fn start(&mut self, model: &Model, process: usize, count: u64) {
+        let step = &model.processes[process];
+        for &(item, qty) in &step.input {
+            self.stocks[item] -= qty * count;
+        }
+        let end = self.time + step.time;
+        match self.running.binary_search_by(|probe| probe.cmp(&(end, process, count))) {
+            Ok(at) | Err(at) => self.running.insert(at, (end, process, count)),
+        }
+        self.makespan = self.makespan.max(end);
+        self.log.push((step.id.clone(), count, self.time));
+    }
+
+    /// Jumps to the next completion and collects every output due then.
+    pub fn advance(&mut self, model: &Model) -> bool {
+        let Some(&(end, _, _)) = self.running.first() else {
+            return false;
+        };
+        self.time = end;
+        while let Some(&(done, p, count)) = self.running.first() {
+            if done != end {
+                break;
+            }
+            self.running.remove(0);
+            for &(item, qty) in &model.processes[p].output {
+                self.stocks[item] += qty * count;
+            }
+        }
+        true
+    }
+
+    /// Moves forward to `time`, collecting the outputs of every run done by then.
+    pub fn release_until(&mut self, model: &Model, time: u64) {
+        while let Some(&(end, p, count)) = self.running.first() {
+            if end > time {
+                break;
+            }
+            self.running.remove(0);
+            for &(item, qty) in &model.processes[p].output {
+                self.stocks[item] += qty * count;
+            }
+        }
+        self.time = self.time.max(time);
+    }
+
+    /// Every set of starts feasible at once at the current time and ending by
+    /// the horizon, the empty one included. The flag is false when `MAX_COMBINATIONS` cut the list short.
+    pub fn combinations(&self, model: &Model, timer_flag: &Arc<AtomicBool>) -> (Vec<Vec<(usize, u64)>>, bool) {
+        fn generate(
+            model: &Model,
+            time: u64,
+            process: usize,
+            stocks: &mut Vec<u64>,
+            current: &mut Vec<(usize, u64)>,
+            result: &mut Vec<Vec<(usize, u64)>>,
+            timer_flag: &Arc<AtomicBool>,
+        ) {
+            if timer_flag.load(AtomicOrdering::SeqCst) || result.len() >= MAX_COMBINATIONS {
+                return;
+            }
+            if process == model.processes.len() {
+                result.push(current.clone());
+                return;
+            }
+
+            let max_runs = if model.ends_in_time(process, time) { model.max_runs(process, stocks) } else { 0 };
+            if max_runs == u64::MAX {
+                generate(model, time, process + 1, stocks, current, result, timer_flag);
+                return;
+            }
+            /* full batches first, so a truncated list still holds the greedy choices */
+            for times in (1..=max_runs).rev() {
+                for &(item, qty) in &model.processes[process].input {
+                    stocks[item] -= qty * times;
+                }
+                current.push((process, times));
+                generate(model, time, process + 1, stocks, current, result, timer_flag);
+                current.pop();
+                for &(item, qty) in &model.processes[process].input {
+                    stocks[item] += qty * times;
+                }
+            }
+            generate(model, time, process + 1, stocks, current, result, timer_flag);
+        }
+
+        let mut result = Vec::new();
+        generate(model, self.time, 0, &mut self.stocks.clone(), &mut Vec::new(), &mut result, timer_flag);
+        let complete = result.len() < MAX_COMBINATIONS;
+        (result, complete)
+    }
+
+    /// Up to `count` distinct starting sets drawn without enumerating them
+    /// all: the greedy one (largest batches in process order) and the empty
+    /// one, then sets that take the processes in a random order, each for
+    /// none, all, or a random part of the runs that still fit.
+    pub fn sample_combinations(&self, model: &Model, count: usize, rng: &mut impl Rng) -> Vec<Vec<(usize, u64)>> {
+        let draw = |order: &[usize], rng: &mut dyn FnMut(u64) -> u64| {
+            let mut stocks = self.stocks.clone();
+            let mut combination = Vec::new();
+            for &process in order {
+                if !model.ends_in_time(process, self.time) {
+                    continue;
+                }
+                let runs = match model.max_runs(process, &stocks) {
+                    /* processes without inputs are not part of the starting sets */
+                    0 | u64::MAX => continue,
+                    runs => rng(runs),
+                };
+                if runs > 0 {
+                    for &(item, qty) in &model.processes[process].input {
+                        stocks[item] -= qty * runs;
+                    }
+                    combination.push((process, runs));
+                }
+            }
+            combination.sort_unstable();
+            combination
+        };
+
+        let mut order: Vec<usize> = (0..model.processes.len()).collect();
+        let mut seen = HashSet::new();
+        let mut result = Vec::new();
+        for combination in [draw(&order, &mut |runs| runs), Vec::new()] {
+            if seen.insert(combination.clone()) {
+                result.push(combination);
+            }
+        }
+        /* small sets run out of new draws long before the attempts do */
+        for _ in 0..count * 4 {
+            if result.len() >= count {
+                break;
+            }
+            order.shuffle(rng);
+            let combination = draw(&order, &mut |runs| match rng.gen_range(0..3) {
+                0 => 0,
+                1 => runs,
+                _ => rng.gen_range(1..=runs),
+            });
+            if seen.insert(combination.clone()) {
+                result.push(combination);
+            }
+        }
+        result
+    }
+
+    /// Starts one combination and moves on to the next completion, if any.
+    pub fn child(&self, model: &Model, combination: &[(usize, u64)]) -> Option<Node> {
+        let mut child = self.clone();
+        for &(process, count) in combination {
+            child.start(model, process, count);
+        }
+        if child.advance(model) { Some(child) } else { None }
+    }
+
+    /// Starts each combination and moves on to the next completion.
+    pub fn successors(&self, model: &Model, timer_flag: &Arc<AtomicBool>) -> (Vec<Node>, bool) {
+        let (combinations, complete) = self.combinations(model, timer_flag);
+        let children = combinations.iter().filter_map(|combination| self.child(model, combination)).collect();
+        (children, complete)
+    }
+}
+
+/// Up to `count` evenly spread starting sets out of `combinations`, in the
+/// order of `Node::combinations`; the first (largest batches) and the last
+/// (start nothing) are always kept.
+pub fn spread(combinations: Vec<Vec<(usize, u64)>>, count: usize) -> Vec<Vec<(usize, u64)>> {
+    if combinations.len() <= count {
+        return combinations;
+    }
+    let stride = combinations.len() as f64 / (count - 1) as f64;
+    let mut picked: Vec<Vec<(usize, u64)>> = (0..count - 1).map(|i| combinations[(i as f64 * stride) as usize].clone()).collect();
+    picked.push(combinations.last().unwrap().clone());
+    picked
+}
@@ -1,92 +1,352 @@
 use crate::Data;
 use crate::Process;
 use crate::delay;
+use crate::progress::Reporter;
+use crate::search::{Model, Node};
+use crate::stock_scores;
+use rand::Rng;
 use std::collections::HashMap;
+use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, Ordering as AtomicOrdering};
 use std::time::Instant;
 
-pub fn sgs_algorithm(mut data: Data, delay: u64) -> (Data, u64, Vec<(String, u64, u64)>) {
-    let timer_flag = delay::start_timer(std::time::Duration::from_secs(delay as u64));
-    let start = Instant::now();
+/// Priority of a process for the greedy schedule: objective gain, minus half
+/// of every other input it consumes.
+pub fn score_process(process: &Process, _stocks: &HashMap<String, u64>, objectives: &[String]) -> i64 {
+    let mut score = 0;
 
-    fn score_process(process: &Process, stocks: &HashMap<String, u64>, objectives: &Vec<String>) -> i64 {
-        let mut score = 0;
+    for (output_item, output_amount) in &process.output {
+        if objectives.contains(output_item) {
+            score += *output_amount as i64;
+        }
+    }
 
-        for (output_item, output_amount) in &process.output {
-            if objectives.contains(output_item) {
-                score += *output_amount as i64;
-            }
+    for (input_item, input_amount) in &process.input {
+        if objectives.contains(input_item) {
+            score -= *input_amount as i64;
+        } else {
+            score -= (*input_amount as i64) / 2;
         }
+    }
+
+    score
+}
+
+/// Stock levels over time for the serial generation scheme: `levels[k]` holds
+/// from `times[k]` until the next breakpoint. Runs are placed one at a time,
+/// each at the earliest breakpoint from which its inputs stay available for
+/// good, so the runs already placed are never invalidated.
+#[derive(Debug, Clone)]
+pub struct Timeline {
+    pub times: Vec<u64>,
+    pub levels: Vec<Vec<u64>>,
+    pub makespan: u64,
+    /* (process, count, start) in the order they were placed */
+    pub runs: Vec<(usize, u64, u64)>,
+}
 
-        for (input_item, input_amount) in &process.input {
-            if objectives.contains(input_item) {
-                score -= *input_amount as i64;
-            } else {
-                score -= (*input_amount as i64) / 2;
+impl Timeline {
+    pub fn new(model: &Model) -> Self {
+        Timeline { times: vec![0], levels: vec![model.initial.clone()], makespan: 0, runs: Vec::new() }
+    }
+
+    /// Earliest breakpoint where `process` can start at least once, with the
+    /// most runs that fit there. Availability only grows with time, so it is
+    /// found by walking back from the end while the runs still fit. None when
+    /// the run would end after the horizon.
+    pub fn earliest(&self, model: &Model, process: usize) -> Option<(usize, u64)> {
+        let step = &model.processes[process];
+        if step.input.is_empty() {
+            return model.ends_in_time(process, 0).then_some((0, 1));
+        }
+        let mut lowest: Vec<u64> = step.input.iter().map(|_| u64::MAX).collect();
+        let mut found = None;
+        for k in (0..self.times.len()).rev() {
+            for (slot, &(item, _)) in lowest.iter_mut().zip(&step.input) {
+                *slot = (*slot).min(self.levels[k][item]);
+            }
+            let runs = lowest.iter().zip(&step.input).map(|(&level, &(_, qty))| level / qty).min().unwrap();
+            if runs == 0 {
+                break;
             }
+            found = Some((k, runs));
         }
-
-        score
+        found.filter(|&(k, _)| model.ends_in_time(process, self.times[k]))
     }
 
-    let mut total_simulated_time = 0;
-    let mut execution_log = Vec::new();
+    fn breakpoint(&mut self, time: u64) -> usize {
+        match self.times.binary_search(&time) {
+            Ok(k) => k,
+            Err(k) => {
+                let level = self.levels[k - 1].clone();
+                self.times.insert(k, time);
+                self.levels.insert(k, level);
+                k
+            }
+        }
+    }
 
-    while !timer_flag.load(AtomicOrdering::SeqCst) {
-        let mut eligible_processes = vec![];
-        for process in &data.processes {
-            let mut is_eligible = true;
-            for (input_item, input_amount) in &process.input {
-                if let Some(stock) = data.stocks.get(input_item) {
-                    if stock < input_amount {
-                        is_eligible = false;
-                        break;
-                    }
-                } else {
-                    is_eligible = false;
-                    break;
-                }
+    /// Places `count` runs of `process` at breakpoint `k`, as found by `earliest`.
+    pub fn place(&mut self, model: &Model, process: usize, count: u64, k: usize) {
+        let step = &model.processes[process];
+        let start = self.times[k];
+        let end = start + step.time;
+        let done = self.breakpoint(end);
+        for level in &mut self.levels[k..] {
+            for &(item, qty) in &step.input {
+                level[item] -= qty * count;
             }
-            if is_eligible {
-                eligible_processes.push(process.clone());
+        }
+        for level in &mut self.levels[done..] {
+            for &(item, qty) in &step.output {
+                level[item] += qty * count;
             }
         }
+        self.makespan = self.makespan.max(end);
+        self.runs.push((process, count, start));
+    }
 
-        if eligible_processes.is_empty() {
-            break;
+    pub fn stocks(&self) -> &[u64] {
+        self.levels.last().unwrap()
+    }
+
+    /// The runs as a log, sorted by start time.
+    pub fn log(&self, model: &Model) -> Vec<(String, u64, u64)> {
+        let mut log: Vec<(String, u64, u64)> = self.runs.iter()
+            .map(|&(process, count, start)| (model.processes[process].id.clone(), count, start))
+            .collect();
+        log.sort_by_key(|(_, _, start)| *start);
+        log
+    }
+}
+
+/// Order in which eligible processes are started.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Rule {
+    /// `score_process`: objective gain minus the inputs consumed
+    Score,
+    /// Shortest process first
+    ShortestDelay,
+    /// Processes whose outputs are the fewest steps away from an objective first
+    ObjectiveDistance,
+    /// Processes feeding the most other processes first
+    MostSuccessors,
+    /// A new random order on every pass
+    Random,
+}
+
+/// How the schedule is generated.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Scheme {
+    /// Run by run, each at the earliest start where its inputs stay available
+    Serial,
+    /// Completion by completion, starting whatever fits before moving on
+    Parallel,
+}
+
+/* Walks of the priority list by a serial pass */
+const ROUNDS: usize = 8;
+/* Completions simulated by a parallel pass */
+const COMPLETIONS: usize = 1_000;
+
+/* Higher first */
+fn priorities(data: &Data, model: &Model, rule: Rule) -> Vec<f64> {
+    match rule {
+        Rule::Score => data.processes.iter().map(|process| score_process(process, &data.stocks, &data.objectives) as f64).collect(),
+        Rule::ShortestDelay => model.processes.iter().map(|step| -(step.time as f64)).collect(),
+        Rule::ObjectiveDistance => {
+            let scores = stock_scores::precompute_stock_scores(data);
+            model.processes.iter()
+                .map(|step| step.output.iter().map(|&(item, _)| *scores.get(&model.names[item]).unwrap_or(&0)).max().unwrap_or(0) as f64)
+                .collect()
         }
+        Rule::MostSuccessors => model.processes.iter()
+            .map(|step| {
+                model.processes.iter()
+                    .filter(|other| other.input.iter().any(|&(item, _)| step.output.iter().any(|&(made, _)| made == item)))
+                    .count() as f64
+            })
+            .collect(),
+        Rule::Random => vec![0.0; model.processes.len()],
+    }
+}
 
-        eligible_processes.sort_by_key(|process| -score_process(process, &data.stocks, &data.objectives));
+/* The first pass follows the rule as it is; later ones shuffle it with
+ * `noise` relative to the spread of the priorities. The random rule draws a
+ * new order on every pass, whatever the noise. */
+fn order(base: &[f64], noise: f64, pass: u64, rule: Rule, rng: &mut impl Rng) -> Vec<usize> {
+    let spread = base.iter().cloned().fold(0.0, |acc: f64, priority| acc.max(priority.abs())).max(1.0);
+    let noisy: Vec<f64> = base.iter().map(|&priority| {
+        if rule == Rule::Random {
+            rng.gen::<f64>()
+        } else if pass > 0 {
+            priority + noise * spread * rng.gen_range(-1.0..1.0)
+        } else {
+            priority
+        }
+    }).collect();
+    let mut order: Vec<usize> = (0..base.len()).collect();
+    order.sort_by(|&a, &b| noisy[b].partial_cmp(&noisy[a]).unwrap_or(std::cmp::Ordering::Equal));
+    order
+}
 
-        let selected_process = eligible_processes.first().unwrap();
+/* Every run that fits on the first pass and without noise, sometimes fewer on the others */
+fn count(runs: u64, noise: f64, pass: u64, rng: &mut impl Rng) -> u64 {
+    if pass == 0 || noise == 0.0 || rng.gen_bool(0.5) { runs } else { rng.gen_range(1..=runs) }
+}
 
-        let mut max_executions = u64::MAX;
-        for (input_item, input_amount) in &selected_process.input {
-            if let Some(stock) = data.stocks.get(input_item) {
-                max_executions = max_executions.min(stock / input_amount);
+/// Serial SGS: the processes are taken in priority order, each placed at its
+/// earliest start, and the list is walked again as long as something fits.
+fn serial(model: &Model, order: &[usize], noise: f64, pass: u64, rng: &mut impl Rng) -> (u64, Vec<u64>, Vec<(String, u64, u64)>) {
+    let mut timeline = Timeline::new(model);
+    for _ in 0..ROUNDS {
+        let mut placed = false;
+        for &process in order {
+            if let Some((k, runs)) = timeline.earliest(model, process) {
+                timeline.place(model, process, count(runs, noise, pass, rng), k);
+                placed = true;
             }
         }
+        if !placed {
+            break;
+        }
+    }
+    (timeline.makespan, timeline.stocks().to_vec(), timeline.log(model))
+}
 
-        for (input_item, input_amount) in &selected_process.input {
-            if let Some(stock) = data.stocks.get_mut(input_item) {
-                *stock -= input_amount * max_executions;
+/// Parallel SGS from `node`: at every completion, the eligible processes are
+/// started in `order` as long as their inputs are there, `count` runs out of
+/// those that fit, then time jumps to the next completion. Stops when nothing
+/// runs any more, after `completions` completions, or once time reaches
+/// `until`.
+pub fn parallel_pass(
+    model: &Model,
+    mut node: Node,
+    order: &[usize],
+    completions: usize,
+    until: u64,
+    mut count: impl FnMut(u64) -> u64,
+    timer_flag: &Arc<AtomicBool>,
+) -> Node {
+    for _ in 0..completions {
+        if timer_flag.load(AtomicOrdering::SeqCst) || node.time >= until {
+            break;
+        }
+        for &process in order {
+            /* processes without inputs start once per completion */
+            let runs = match node.max_runs(model, process) {
+                u64::MAX => 1,
+                runs => runs,
+            };
+            if runs > 0 {
+                node.start(model, process, count(runs));
             }
         }
+        if !node.advance(model) {
+            break;
+        }
+    }
+    node
+}
 
-        for (output_item, output_amount) in &selected_process.output {
-            *data.stocks.entry(output_item.clone()).or_insert(0) += output_amount * max_executions;
+fn parallel(model: &Model, order: &[usize], noise: f64, pass: u64, rng: &mut impl Rng, timer_flag: &Arc<AtomicBool>) -> (u64, Vec<u64>, Vec<(String, u64, u64)>) {
+    let node = parallel_pass(model, Node::root(model), order, COMPLETIONS, u64::MAX, |runs| count(runs, noise, pass, rng), timer_flag);
+    let stocks = node.settled(model);
+    (node.makespan, stocks, node.log)
+}
+
+/// The original one-at-a-time greedy: the eligible process with the best
+/// `score_process` runs as many times as its inputs allow, and the next one
+/// is chosen once it is done.
+fn sequential(data: &Data, model: &Model, timer_flag: &Arc<AtomicBool>) -> (u64, Vec<u64>, Vec<(String, u64, u64)>) {
+    let scores: Vec<i64> = data.processes.iter().map(|process| score_process(process, &data.stocks, &data.objectives)).collect();
+    let mut node = Node::root(model);
+    for _ in 0..COMPLETIONS {
+        if timer_flag.load(AtomicOrdering::SeqCst) {
+            break;
         }
+        let Some(process) = (0..model.processes.len())
+            .filter(|&process| node.max_runs(model, process) > 0)
+            .max_by_key(|&process| (scores[process], std::cmp::Reverse(process)))
+        else {
+            break;
+        };
+        let runs = match node.max_runs(model, process) {
+            u64::MAX => 1,
+            runs => runs,
+        };
+        node.start(model, process, runs);
+        node.advance(model);
+    }
+    let stocks = node.settled(model);
+    (node.makespan, stocks, node.log)
+}
 
-        execution_log.push((selected_process.id.clone(), max_executions, total_simulated_time));
+/// Every process in `score_process` order, as many runs as fit at its
+/// earliest start, round after round until nothing fits: the first serial
+/// pass, a quick complete schedule to seed the local searches.
+pub fn greedy_log(data: &Data) -> Vec<(String, u64, u64)> {
+    let model = Model::new(data);
+    let mut rng = rand::thread_rng();
+    let order = order(&priorities(data, &model, Rule::Score), 0.0, 0, Rule::Score, &mut rng);
+    serial(&model, &order, 0.0, 0, &mut rng).2
+}
 
-        total_simulated_time += selected_process.time;
+/// Multi-pass schedule generation. The better of the idle schedule and the
+/// original sequential greedy is the incumbent to beat; the first pass
+/// follows `rule`, and the following ones sample priority orders and batch
+/// sizes around it with `noise`, keeping the best schedule of all until the
+/// delay elapses. Without noise and with a fixed rule every pass would be the
+/// same, so one is enough.
+pub fn sgs_algorithm(data: Data, delay: u64, scheme: Scheme, rule: Rule, noise: f64, progress: &Reporter) -> Option<(u64, HashMap<String, u64>, Vec<(String, u64, u64)>)> {
+    let model = Model::new(&data);
+    if model.processes.is_empty() {
+        return None;
+    }
+    let timer_flag = delay::start_timer(std::time::Duration::from_secs(delay));
+    let start = Instant::now();
+    let mut rng = rand::thread_rng();
+    let base = priorities(&data, &model, rule);
+    let deterministic = rule != Rule::Random && noise == 0.0;
 
+    /* doing nothing keeps the initial stocks, which a greedy may spend for less */
+    let mut best = (0, model.initial.clone(), Vec::new());
+    let mut best_objective = model.objective(&best.1);
+    let greedy = sequential(&data, &model, &timer_flag);
+    let objective = model.objective(&greedy.1);
+    if objective > best_objective {
+        best_objective = objective;
+        best = greedy;
     }
+    let mut pass: u64 = 0;
 
-    let elapsed = start.elapsed();
+    while !timer_flag.load(AtomicOrdering::SeqCst) {
+        let order = order(&base, noise, pass, rule, &mut rng);
+        let candidate = match scheme {
+            Scheme::Serial => serial(&model, &order, noise, pass, &mut rng),
+            Scheme::Parallel => parallel(&model, &order, noise, pass, &mut rng, &timer_flag),
+        };
+        /* a pass cut short by the timer is a truncated schedule, not a candidate */
+        if timer_flag.load(AtomicOrdering::SeqCst) {
+            break;
+        }
+        let objective = model.objective(&candidate.1);
+        if objective > best_objective || (objective == best_objective && candidate.0 < best.0) {
+            best_objective = objective;
+            best = candidate;
+        }
+        pass += 1;
+        progress.tick(pass, best_objective, best.0, || format!("pass {}", pass));
+        if deterministic {
+            break;
+        }
+    }
 
-    println!("SGS* executed in: {}.{:03} seconds\n", elapsed.as_secs(), elapsed.subsec_millis());
+    progress.finish(pass, best_objective, best.0, format!("{} passes", pass));
 
-    (data, total_simulated_time, execution_log)
-}
+    let elapsed = start.elapsed();
+    say!("SGS* executed in: {}.{:03} seconds\n", elapsed.as_secs(), elapsed.subsec_millis());
 
+    let (makespan, stocks, log) = best;
+    Some((makespan, model.stocks_map(&stocks), log))
+}
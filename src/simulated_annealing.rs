@@ -0,0 +1,166 @@
+extern crate rand;
+use rand::prelude::*;
+use crate::Data;
+use crate::delay;
+use crate::sgs;
+use crate::progress::Reporter;
+use crate::simulator::{Log, Schedule};
+use rand::Rng;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use std::sync::atomic::Ordering as AtomicOrdering;
+
+/* Final temperature, relative to the initial one */
+const COOLED: f64 = 1e-3;
+/* Share of the budget without a new best before the reheating schedule reheats */
+const STALL: f64 = 0.05;
+/* Moves sampled to scale the initial temperature */
+const CALIBRATION_MOVES: usize = 200;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Cooling {
+    /// T0 * (Tend / T0) ^ progress
+    Geometric,
+    /// From T0 down to Tend in a straight line
+    Linear,
+    /// Geometric, restarted from a lower peak whenever the best stalls
+    Reheating,
+}
+
+trait Neighbor {
+    fn neighbor(&self, data: &Data, rng: &mut ThreadRng) -> Self;
+}
+
+impl Neighbor for Schedule {
+    /// Insert, remove, swap or shift one entry, then replay the result.
+    fn neighbor(&self, data: &Data, rng: &mut ThreadRng) -> Self {
+        let mut log = self.log.clone();
+        let times = self.event_times(data);
+        let at = |rng: &mut ThreadRng| times[rng.gen_range(0..times.len())];
+
+        /* swapping needs two entries, removing and shifting one */
+        let moves = match log.len() { 0 => 1, 1 => 3, _ => 4 };
+        match rng.gen_range(0..moves) {
+            0 => {
+                /* anywhere among the entries of the same time, which the replay runs in order */
+                let process = &data.processes[rng.gen_range(0..data.processes.len())];
+                let count = 1u64 << rng.gen_range(0..8);
+                log.insert(rng.gen_range(0..=log.len()), (process.id.clone(), count, at(rng)));
+            }
+            1 => {
+                log.remove(rng.gen_range(0..log.len()));
+            }
+            3 => {
+                let (a, b) = (rng.gen_range(0..log.len()), rng.gen_range(0..log.len()));
+                let start = log[a].2;
+                log[a].2 = log[b].2;
+                log[b].2 = start;
+            }
+            _ => {
+                let entry = rng.gen_range(0..log.len());
+                let moved = (log[entry].0.clone(), log[entry].1, at(rng));
+                log.remove(entry);
+                log.insert(rng.gen_range(0..=log.len()), moved);
+            }
+        }
+
+        Schedule::new(data, log)
+    }
+}
+
+fn temperature(cooling: Cooling, initial: f64, progress: f64) -> f64 {
+    let end = initial * COOLED;
+    match cooling {
+        Cooling::Linear => initial + (end - initial) * progress,
+        Cooling::Geometric | Cooling::Reheating => initial * (end / initial).powf(progress),
+    }
+}
+
+/// Simulated annealing over complete schedules. Each neighbor inserts,
+/// removes, swaps or shifts one run and is replayed through the simulator,
+/// which postpones or drops what no longer fits. The temperature follows the
+/// share of the time budget spent, from a start scaled on sampled moves, so the
+/// schedule cools the same way whatever the iteration speed.
+pub fn simulated_annealing(data: &Data, cooling: Cooling, max_delay: u64, progress: &Reporter) -> (HashMap<String, u64>, u64, Log) {
+    let mut rng = rand::thread_rng();
+    let budget = Duration::from_secs(max_delay).max(Duration::from_millis(1));
+    let timer_flag = delay::start_timer(budget);
+    let start = Instant::now();
+
+    let idle = Schedule::new(data, Vec::new());
+    let seeded = Schedule::new(data, sgs::greedy_log(data));
+    let mut current = if seeded.better_than(&idle) { seeded } else { idle };
+    let mut best = current.clone();
+
+    if data.processes.is_empty() {
+        return (best.stocks, best.makespan, best.log);
+    }
+
+    /* accept an average move losing objective with probability 1/e at the
+     * start; makespan-only moves would scale it down to the tie-break weight */
+    let mut worsening = Vec::new();
+    for _ in 0..CALIBRATION_MOVES {
+        let neighbor = current.neighbor(data, &mut rng);
+        if neighbor.objective < current.objective {
+            worsening.push((current.objective - neighbor.objective) as f64);
+        }
+    }
+    let mut initial = if worsening.is_empty() { 1.0 } else { (worsening.iter().sum::<f64>() / worsening.len() as f64).max(1.0) };
+
+    let mut phase_start = 0.0;
+    let mut last_best = 0.0;
+    let mut temp = initial;
+    let mut iterations: u64 = 0;
+
+    loop {
+        if timer_flag.load(AtomicOrdering::SeqCst) {
+            say!("Timer elapsed, stopping optimization");
+            break;
+        }
+
+        let elapsed = start.elapsed().as_secs_f64() / budget.as_secs_f64();
+        if cooling == Cooling::Reheating && elapsed - last_best > STALL && elapsed < 1.0 {
+            initial *= 0.5;
+            phase_start = elapsed;
+            last_best = elapsed;
+            current = best.clone();
+        }
+        let phase = ((elapsed - phase_start) / (1.0 - phase_start)).clamp(0.0, 1.0);
+        temp = temperature(cooling, initial, phase);
+
+        let candidate = current.neighbor(data, &mut rng);
+        let delta = candidate.energy - current.energy;
+        if delta <= 0.0 || rng.gen::<f64>() < (-delta / temp).exp() {
+            current = candidate;
+            if current.better_than(&best) {
+                best = current.clone();
+                last_best = elapsed;
+            }
+        }
+
+        iterations += 1;
+        progress.tick(iterations, best.objective, best.makespan, || format!("temp {:.3e}", temp));
+    }
+
+    progress.finish(iterations, best.objective, best.makespan, format!("temp {:.3e}", temp));
+
+    let elapsed = start.elapsed();
+    say!("Simulated annealing executed in: {}.{:03} seconds\n", elapsed.as_secs(), elapsed.subsec_millis());
+
+    (best.stocks, best.makespan, best.log)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulator;
+
+    #[test]
+    fn reaches_the_ikea_optimum() {
+        let data = crate::load_data("resources/ikea").unwrap();
+        /* the seed makes nothing here, and the walk takes around 1e5 moves
+         * to find the cabinet, which an unoptimized build needs seconds for */
+        let (stocks, _, _) = simulated_annealing(&data, Cooling::Geometric, 5, &Reporter::disabled());
+        assert_eq!(simulator::objective(&data.objectives, &stocks), 1);
+    }
+}
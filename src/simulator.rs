@@ -0,0 +1,322 @@
+use crate::Data;
+use crate::Process;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+
+/// `(process, count, start)` entries, the format every solver logs and `gen_file` writes.
+pub type Log = Vec<(String, u64, u64)>;
+
+/* Makespan weight in the energy of a schedule, small enough to only break objective ties */
+pub const MAKESPAN_WEIGHT: f64 = 1e-6;
+
+#[derive(Debug, Clone)]
+pub struct Outcome {
+    pub stocks: HashMap<String, u64>,
+    pub makespan: u64,
+    pub objective: u64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Validity {
+    Valid,
+    /// The schedule is feasible but the claimed finish time or stocks were wrong
+    Corrected,
+    /// Infeasible entries were postponed or dropped
+    Repaired { shifted: u64, dropped: u64 },
+    /// Nothing of the schedule could be kept
+    Rejected,
+}
+
+impl Validity {
+    pub fn describe(&self) -> String {
+        match self {
+            Validity::Valid => "valid".to_string(),
+            Validity::Corrected => "valid (claimed time/stocks corrected)".to_string(),
+            Validity::Repaired { shifted, dropped } => format!("repaired ({} runs shifted, {} dropped)", shifted, dropped),
+            Validity::Rejected => "rejected".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Validated {
+    pub log: Log,
+    pub outcome: Outcome,
+    pub validity: Validity,
+}
+
+/// A complete schedule, always the feasible output of `repair`, as the local
+/// searches move from one to the next.
+#[derive(Debug, Clone)]
+pub struct Schedule {
+    pub log: Log,
+    pub objective: u64,
+    pub makespan: u64,
+    pub stocks: HashMap<String, u64>,
+    /// Lower is better. Only the objective counts: rewarding intermediate
+    /// stocks makes buying everything look like progress on files such as pomme.
+    pub energy: f64,
+}
+
+impl Schedule {
+    pub fn new(data: &Data, mut log: Log) -> Self {
+        /* stable: among runs of the same time, the replay keeps the log order */
+        log.sort_by_key(|(_, _, start)| *start);
+        let (log, outcome, _, _) = repair(data, &log);
+        let energy = -(outcome.objective as f64) + MAKESPAN_WEIGHT * outcome.makespan as f64;
+        Schedule { log, objective: outcome.objective, makespan: outcome.makespan, stocks: outcome.stocks, energy }
+    }
+
+    pub fn better_than(&self, other: &Schedule) -> bool {
+        self.objective > other.objective || (self.objective == other.objective && self.makespan < other.makespan)
+    }
+
+    pub fn event_times(&self, data: &Data) -> Vec<u64> {
+        event_times(data, &self.log)
+    }
+}
+
+/// Times where something changes: the start, and every start and completion
+/// of `log`, sorted and without duplicates.
+pub fn event_times(data: &Data, log: &Log) -> Vec<u64> {
+    let durations: HashMap<&str, u64> = data.processes.iter().map(|process| (process.id.as_str(), process.time)).collect();
+    let mut times = vec![0];
+    for (id, _, start) in log {
+        times.push(*start);
+        times.push(start + durations.get(id.as_str()).unwrap_or(&0));
+    }
+    times.sort_unstable();
+    times.dedup();
+    times
+}
+
+pub fn objective(objectives: &[String], stocks: &HashMap<String, u64>) -> u64 {
+    objectives.iter().map(|obj| *stocks.get(obj).unwrap_or(&0)).sum()
+}
+
+fn max_runs(process: &Process, stocks: &HashMap<String, u64>) -> u64 {
+    process.input.iter()
+        .map(|(item, qty)| stocks.get(item).unwrap_or(&0) / qty)
+        .min()
+        .unwrap_or(u64::MAX)
+}
+
+fn ends_after(data: &Data, index: usize, start: u64) -> bool {
+    data.horizon.is_some_and(|horizon| start.saturating_add(data.processes[index].time) > horizon)
+}
+
+/* Running batches are keyed by completion time; outputs are added when they complete */
+struct Replay<'a> {
+    processes: &'a [Process],
+    index: HashMap<&'a str, usize>,
+    stocks: HashMap<String, u64>,
+    running: BinaryHeap<Reverse<(u64, usize, u64)>>,
+    makespan: u64,
+}
+
+impl<'a> Replay<'a> {
+    fn new(data: &'a Data) -> Self {
+        Replay {
+            processes: &data.processes,
+            index: data.processes.iter().enumerate().map(|(i, p)| (p.id.as_str(), i)).collect(),
+            stocks: data.stocks.clone(),
+            running: BinaryHeap::new(),
+            makespan: 0,
+        }
+    }
+
+    fn release_until(&mut self, time: u64) {
+        while let Some(Reverse((end, index, count))) = self.running.peek().cloned() {
+            if end > time {
+                break;
+            }
+            self.running.pop();
+            for (item, qty) in &self.processes[index].output {
+                *self.stocks.entry(item.clone()).or_insert(0) += qty * count;
+            }
+        }
+    }
+
+    fn next_completion(&self) -> Option<u64> {
+        self.running.peek().map(|Reverse((end, _, _))| *end)
+    }
+
+    fn start(&mut self, index: usize, count: u64, time: u64) {
+        let process = &self.processes[index];
+        for (item, qty) in &process.input {
+            *self.stocks.get_mut(item).unwrap() -= qty * count;
+        }
+        self.running.push(Reverse((time + process.time, index, count)));
+        self.makespan = self.makespan.max(time + process.time);
+    }
+
+    fn finish(mut self, objectives: &[String]) -> Outcome {
+        self.release_until(u64::MAX);
+        Outcome {
+            objective: objective(objectives, &self.stocks),
+            stocks: self.stocks,
+            makespan: self.makespan,
+        }
+    }
+}
+
+/// Replays a log under the concurrent semantics: inputs are consumed when a
+/// run starts and its outputs arrive at `start + time`, which may not be after
+/// the horizon.
+pub fn simulate(data: &Data, log: &Log) -> Result<Outcome, String> {
+    let mut replay = Replay::new(data);
+    let mut previous_time = 0;
+
+    for (entry, (id, count, time)) in log.iter().enumerate() {
+        if *time < previous_time {
+            return Err(format!("entry {}: time {} goes back before {}", entry, time, previous_time));
+        }
+        previous_time = *time;
+        replay.release_until(*time);
+
+        let Some(&index) = replay.index.get(id.as_str()) else {
+            return Err(format!("entry {}: unknown process '{}'", entry, id));
+        };
+        if ends_after(data, index, *time) {
+            return Err(format!("entry {}: '{}' started at {} ends after the horizon", entry, id, time));
+        }
+        if max_runs(&data.processes[index], &replay.stocks) < *count {
+            return Err(format!("entry {}: not enough stock to run '{}' {} times at {}", entry, id, count, time));
+        }
+        replay.start(index, *count, *time);
+    }
+
+    Ok(replay.finish(&data.objectives))
+}
+
+/// Rebuilds a feasible log: every run that cannot start at its time is
+/// postponed to the next completion, and dropped once nothing is running or
+/// it would end after the horizon.
+pub fn repair(data: &Data, log: &Log) -> (Log, Outcome, u64, u64) {
+    let mut replay = Replay::new(data);
+    let mut pending: BinaryHeap<Reverse<(u64, usize, u64)>> = BinaryHeap::new();
+    let mut repaired = Vec::new();
+    let mut shifted = 0;
+    let mut dropped = 0;
+
+    for (entry, (_, count, time)) in log.iter().enumerate() {
+        pending.push(Reverse((*time, entry, *count)));
+    }
+
+    while let Some(Reverse((time, entry, count))) = pending.pop() {
+        replay.release_until(time);
+
+        let (id, _, original_time) = &log[entry];
+        let Some(&index) = replay.index.get(id.as_str()) else {
+            dropped += count;
+            continue;
+        };
+
+        /* postponing only makes it end later */
+        if ends_after(data, index, time) {
+            dropped += count;
+            continue;
+        }
+
+        let runs = max_runs(&data.processes[index], &replay.stocks).min(count);
+        if runs > 0 {
+            replay.start(index, runs, time);
+            repaired.push((id.clone(), runs, time));
+            if time > *original_time {
+                shifted += runs;
+            }
+        }
+
+        let left = count - runs;
+        if left > 0 {
+            match replay.next_completion() {
+                Some(next) => pending.push(Reverse((next, entry, left))),
+                None => dropped += left,
+            }
+        }
+    }
+
+    (repaired, replay.finish(&data.objectives), shifted, dropped)
+}
+
+/// Runs a solver result through the simulator before it is written anywhere.
+pub fn validate(data: &Data, log: &Log, claimed_time: u64, claimed_stocks: &HashMap<String, u64>) -> Validated {
+    let mut sorted = log.clone();
+    sorted.sort_by_key(|(_, _, time)| *time);
+    sorted.retain(|(_, count, _)| *count > 0);
+
+    if let Ok(outcome) = simulate(data, &sorted) {
+        let claims_hold = outcome.makespan == claimed_time
+            && outcome.stocks.iter().all(|(item, qty)| claimed_stocks.get(item).unwrap_or(&0) == qty)
+            && claimed_stocks.iter().all(|(item, qty)| outcome.stocks.get(item).unwrap_or(&0) == qty);
+        let validity = if claims_hold { Validity::Valid } else { Validity::Corrected };
+        return Validated { log: sorted, outcome, validity };
+    }
+
+    let (repaired, outcome, shifted, dropped) = repair(data, &sorted);
+    let validity = if repaired.is_empty() {
+        Validity::Rejected
+    } else {
+        Validity::Repaired { shifted, dropped }
+    };
+    Validated { log: repaired, outcome, validity }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /* a -> b in 5 cycles, b -> c in 3 */
+    fn data() -> Data {
+        crate::inline_data("a:2\nmake_b:(a:1):(b:1):5\nmake_c:(b:1):(c:1):3\noptimize:(c)\n")
+    }
+
+    fn log(entries: &[(&str, u64, u64)]) -> Log {
+        entries.iter().map(|&(id, count, start)| (id.to_string(), count, start)).collect()
+    }
+
+    #[test]
+    fn simulate_valid_log() {
+        let outcome = simulate(&data(), &log(&[("make_b", 2, 0), ("make_c", 2, 5)])).unwrap();
+        assert_eq!((outcome.objective, outcome.makespan), (2, 8));
+    }
+
+    #[test]
+    fn simulate_rejects_early_outputs() {
+        assert!(simulate(&data(), &log(&[("make_b", 1, 0), ("make_c", 1, 4)])).is_err());
+    }
+
+    #[test]
+    fn repair_shifts_runs_to_the_next_completion() {
+        let (repaired, outcome, shifted, dropped) = repair(&data(), &log(&[("make_b", 1, 0), ("make_c", 1, 2)]));
+        assert_eq!(repaired, log(&[("make_b", 1, 0), ("make_c", 1, 5)]));
+        assert_eq!((shifted, dropped), (1, 0));
+        assert_eq!((outcome.objective, outcome.makespan), (1, 8));
+    }
+
+    #[test]
+    fn repair_drops_what_never_fits() {
+        let (repaired, outcome, shifted, dropped) = repair(&data(), &log(&[("make_b", 3, 0), ("make_d", 1, 0), ("make_c", 3, 5)]));
+        assert_eq!(repaired, log(&[("make_b", 2, 0), ("make_c", 2, 5)]));
+        assert_eq!((shifted, dropped), (0, 3));
+        assert_eq!(outcome.objective, 2);
+    }
+
+    #[test]
+    fn repair_drops_runs_past_the_horizon() {
+        let mut data = data();
+        data.horizon = Some(7);
+        let (repaired, _, shifted, dropped) = repair(&data, &log(&[("make_b", 1, 0), ("make_c", 1, 2)]));
+        assert_eq!(repaired, log(&[("make_b", 1, 0)]));
+        assert_eq!((shifted, dropped), (0, 1));
+    }
+
+    #[test]
+    fn validate_reports_repairs() {
+        let validated = validate(&data(), &log(&[("make_c", 1, 2), ("make_b", 1, 0)]), 0, &HashMap::new());
+        assert_eq!(validated.validity, Validity::Repaired { shifted: 1, dropped: 0 });
+        let validated = validate(&data(), &validated.log, 8, &validated.outcome.stocks);
+        assert_eq!(validated.validity, Validity::Valid);
+        assert_eq!(validate(&data(), &log(&[("make_c", 1, 0)]), 0, &HashMap::new()).validity, Validity::Rejected);
+    }
+}
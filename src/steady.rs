@@ -0,0 +1,232 @@
+use crate::Data;
+use crate::ilp;
+use crate::lp;
+use crate::sgs;
+use crate::search::{Model, Node};
+use crate::simulator::Log;
+
+/* Periods tried, in multiples of the shortest one where every process runs */
+const MAX_PERIOD_STEPS: u64 = 2_000;
+/* Events unrolled into a trace before it is cut short */
+const MAX_EVENTS: u64 = 1_000_000;
+/* A period is good enough once its integer counts reach this share of the LP rate */
+const ROUNDING_TOLERANCE: f64 = 0.95;
+/* Periods of quota a process may fall behind before the rest is forgotten */
+const MAX_BACKLOG: u64 = 2;
+
+/// A repeating schedule: every `period` cycles, each process runs `count` times.
+#[derive(Debug, Clone)]
+pub struct Cycle {
+    pub period: u64,
+    pub counts: Vec<(String, u64)>,
+    /// Objective units produced per cycle by the integer counts
+    pub rate: f64,
+    /// Rate of the fractional optimum, an upper bound on any steady state
+    pub lp_rate: f64,
+}
+
+/* Net objective gain of one run */
+fn gain(model: &Model, p: usize) -> f64 {
+    let step = &model.processes[p];
+    let made: u64 = step.output.iter().filter(|(item, _)| model.objectives.contains(item)).map(|(_, qty)| qty).sum();
+    let used: u64 = step.input.iter().filter(|(item, _)| model.objectives.contains(item)).map(|(_, qty)| qty).sum();
+    made as f64 - used as f64
+}
+
+/* Per item: the run-count coefficients of the balance (consumed minus
+ * produced, at most 0 in a steady state) and of the occupancy (units held
+ * during a run and given back at its end, times its duration, at most the
+ * stock there is) */
+fn constraints(model: &Model) -> (Vec<Vec<f64>>, Vec<Vec<f64>>) {
+    let n = model.processes.len();
+    let mut balance = vec![vec![0.0; n]; model.names.len()];
+    let mut occupancy = vec![vec![0.0; n]; model.names.len()];
+    for (p, step) in model.processes.iter().enumerate() {
+        for &(item, qty) in &step.input {
+            balance[item][p] += qty as f64;
+            let returned = step.output.iter().filter(|&&(out, _)| out == item).map(|&(_, qty)| qty).sum::<u64>();
+            occupancy[item][p] += (qty.min(returned) * step.time) as f64;
+        }
+        for &(item, qty) in &step.output {
+            balance[item][p] -= qty as f64;
+        }
+    }
+    (balance, occupancy)
+}
+
+fn fits(coefficients: &[f64], counts: &[u64], limit: f64) -> bool {
+    coefficients.iter().zip(counts).map(|(coefficient, &count)| coefficient * count as f64).sum::<f64>() <= limit + 1e-6
+}
+
+/// Best steady-state rates by LP: runs per cycle of each process, maximizing
+/// the objective produced per cycle, with no stock running out in the long
+/// run and reusable stocks (given back by the process that took them) shared
+/// by the runs in progress. The rates are then rounded to integer counts over
+/// the shortest period that keeps most of the rate.
+pub fn cycle(data: &Data) -> Result<Cycle, String> {
+    let model = Model::new(data);
+    let n = model.processes.len();
+    if n == 0 {
+        return Err("no process to repeat".to_string());
+    }
+    let (balance, occupancy) = constraints(&model);
+
+    let mut rows = Vec::new();
+    let mut limits = Vec::new();
+    for item in 0..model.names.len() {
+        if balance[item].iter().any(|&coefficient| coefficient > 0.0) {
+            rows.push(balance[item].clone());
+            limits.push(0.0);
+        }
+        if occupancy[item].iter().any(|&coefficient| coefficient > 0.0) {
+            rows.push(occupancy[item].clone());
+            limits.push(model.initial[item] as f64);
+        }
+    }
+    let c: Vec<f64> = (0..n).map(|p| gain(&model, p)).collect();
+
+    let (lp_rate, rates) = match lp::maximize(&c, &rows, &limits) {
+        lp::Solution::Unbounded => return Err("the objective can grow without limit".to_string()),
+        lp::Solution::Optimal { value, x } => (value, x),
+    };
+    if lp_rate <= 1e-9 {
+        return Err("nothing can be produced forever: the objective only comes from stocks that run out".to_string());
+    }
+
+    /* the slowest process in the LP optimum runs at least once per period */
+    let bucket = ilp::default_bucket(data);
+    let slowest = rates.iter().cloned().filter(|&rate| rate > 1e-9).fold(f64::INFINITY, f64::min);
+    let shortest = (1.0 / (slowest * bucket as f64) - 1e-6).ceil().max(1.0) * bucket as f64;
+    if shortest >= u64::MAX as f64 {
+        return Err(format!("the slowest process runs once every {:.0} cycles", 1.0 / slowest));
+    }
+    let base = shortest as u64;
+    let mut best: Option<Cycle> = None;
+    for steps in 1..=MAX_PERIOD_STEPS {
+        let Some(period) = steps.checked_mul(base) else {
+            break;
+        };
+        let mut counts: Vec<u64> = rates.iter().map(|rate| (rate * period as f64 + 1e-6).floor() as u64).collect();
+        /* flooring may leave a consumer ahead of its producers: it runs less until they keep up */
+        while let Some(row) = balance.iter().find(|row| !fits(row, &counts, 0.0)) {
+            let Some(p) = (0..n).filter(|&p| row[p] > 0.0 && counts[p] > 0).max_by(|&a, &b| row[a].total_cmp(&row[b])) else {
+                break;
+            };
+            counts[p] -= 1;
+        }
+        let feasible = balance.iter().all(|row| fits(row, &counts, 0.0))
+            && occupancy.iter().zip(&model.initial).all(|(row, &stock)| fits(row, &counts, stock as f64 * period as f64));
+        if !feasible {
+            continue;
+        }
+        let rate = counts.iter().enumerate().map(|(p, &count)| count as f64 * gain(&model, p)).sum::<f64>() / period as f64;
+        if best.as_ref().is_none_or(|best| rate > best.rate + 1e-9) {
+            best = Some(Cycle {
+                period,
+                counts: counts.iter().enumerate()
+                    .filter(|(_, &count)| count > 0)
+                    .map(|(p, &count)| (model.processes[p].id.clone(), count))
+                    .collect(),
+                rate,
+                lp_rate,
+            });
+        }
+        if rate >= ROUNDING_TOLERANCE * lp_rate {
+            break;
+        }
+    }
+    best.filter(|cycle| cycle.rate > 0.0).ok_or_else(|| "no period up to the search limit keeps a positive integer rate".to_string())
+}
+
+/// Unrolls the cycle into a trace. Each process earns the right to its runs
+/// evenly along the period, one more every `period / count` cycles, and at
+/// each event the processes start in SGS priority order as far as their
+/// allowance and the stocks allow, so no buyer spends in one go what the whole
+/// period needs. No run ends after `horizon`. The first periods fill the
+/// pipeline, so the trace's average rate approaches the cycle's only over long
+/// horizons. Long periods would take too many events to reach the horizon,
+/// so the trace stops after `MAX_EVENTS`; the cycle it reached is returned
+/// with it.
+pub fn unroll(data: &Data, cycle: &Cycle, horizon: u64) -> (Log, u64) {
+    let mut model = Model::new(data);
+    model.horizon = model.horizon.min(horizon);
+    let counts: Vec<u64> = model.processes.iter()
+        .map(|step| cycle.counts.iter().find(|(id, _)| *id == step.id).map_or(0, |(_, count)| *count))
+        .collect();
+    let mut order: Vec<usize> = (0..model.processes.len()).filter(|&p| counts[p] > 0).collect();
+    order.sort_by_key(|&p| -sgs::score_process(&data.processes[p], &data.stocks, &data.objectives));
+    /* runs allowed by `time`: the first at once, then evenly along each period */
+    let allowed = |p: usize, time: u64| (counts[p] as u128 * time as u128 / cycle.period as u128) as u64 + 1;
+
+    let mut node = Node::root(&model);
+    let mut started = vec![0u64; model.processes.len()];
+    for _ in 0..MAX_EVENTS {
+        for &p in &order {
+            let allowance = allowed(p, node.time);
+            /* a process too far behind forgets the runs it missed */
+            started[p] = started[p].max(allowance.saturating_sub(MAX_BACKLOG * counts[p]));
+            let runs = node.max_runs(&model, p).min(allowance - started[p]);
+            if runs > 0 {
+                node.start(&model, p, runs);
+                started[p] += runs;
+            }
+        }
+
+        let completion = node.running.first().map_or(u64::MAX, |&(end, _, _)| end);
+        /* the next time some allowance grows: the first t with count * t >= allowance * period */
+        let allowance_grows = order.iter()
+            .map(|&p| {
+                let allowance = allowed(p, node.time) as u128;
+                (allowance * cycle.period as u128).div_ceil(counts[p] as u128).min(u64::MAX as u128) as u64
+            })
+            .min()
+            .unwrap_or(u64::MAX);
+        let next = completion.min(allowance_grows);
+        if next > horizon {
+            return (node.log, horizon);
+        }
+        node.release_until(&model, next);
+    }
+    (node.log, node.time)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulator;
+
+    #[test]
+    fn pomme_cycle_stays_under_the_lp_rate() {
+        let data = crate::load_data("resources/pomme").unwrap();
+        let cycle = cycle(&data).unwrap();
+        assert!(cycle.rate > 0.0);
+        assert!(cycle.rate <= cycle.lp_rate + 1e-9, "{} > {}", cycle.rate, cycle.lp_rate);
+    }
+
+    #[test]
+    fn unrolled_trace_is_accepted() {
+        let mut data = crate::load_data("resources/pomme").unwrap();
+        let cycle = cycle(&data).unwrap();
+        let horizon = 3 * cycle.period;
+        data.horizon = Some(horizon);
+        let (log, reached) = unroll(&data, &cycle, horizon);
+        assert_eq!(reached, horizon);
+        let outcome = simulator::simulate(&data, &log).unwrap();
+        assert!(outcome.objective > 0);
+        assert!(outcome.makespan <= horizon);
+    }
+
+    #[test]
+    fn long_periods_are_unrolled_up_to_the_event_limit() {
+        let mut data = crate::load_data("resources/inception").unwrap();
+        let cycle = cycle(&data).unwrap();
+        assert!(cycle.rate > 0.0 && cycle.rate <= cycle.lp_rate + 1e-9);
+        let horizon = 10 * cycle.period;
+        data.horizon = Some(horizon);
+        let (log, reached) = unroll(&data, &cycle, horizon);
+        assert!(reached < horizon);
+        assert!(!log.is_empty());
+        let outcome = simulator::simulate(&data, &log).unwrap();
+        assert!(outcome.makespan <= horizon);
+    }
+}
@@ -0,0 +1,174 @@
+extern crate rand;
+use crate::Data;
+use crate::delay;
+use crate::ilp;
+use crate::sgs;
+use crate::progress::Reporter;
+use crate::search::Model;
+use crate::simulator::{Log, Schedule};
+use rand::Rng;
+use rand::rngs::ThreadRng;
+use std::collections::HashMap;
+use std::time::Instant;
+use std::sync::atomic::Ordering as AtomicOrdering;
+
+/* Moves sampled per iteration; the full neighborhood is far too large */
+const CANDIDATES: usize = 32;
+/* Iterations an attribute stays tabu: TENURE plus up to one per process */
+const TENURE: u64 = 7;
+/* Iterations without a new best before restarting from a perturbed best */
+const STALL: u64 = 200;
+/* Random moves applied to the best schedule on a restart */
+const PERTURBATION: usize = 8;
+/* Expired attributes are dropped once the list holds this many */
+const MAX_TABU: usize = 4096;
+
+/// A schedule edit. Entries and positions are indices in the current log.
+#[derive(Debug, Clone, Copy)]
+enum Move {
+    Insert { process: usize, count: u64, start: u64, position: usize },
+    Remove { entry: usize },
+    Shift { entry: usize, start: u64 },
+}
+
+/// Tabu moves over the shared schedule of the local searches.
+trait Neighborhood {
+    fn random_move(&self, model: &Model, times: &[u64], rng: &mut ThreadRng) -> Move;
+    fn attributes(&self, model: &Model, bucket: u64, edit: Move) -> Vec<(usize, u64)>;
+    fn apply(&self, data: &Data, model: &Model, edit: Move) -> Self;
+}
+
+impl Neighborhood for Schedule {
+    fn random_move(&self, model: &Model, times: &[u64], rng: &mut ThreadRng) -> Move {
+        let at = times[rng.gen_range(0..times.len())];
+        match if self.log.is_empty() { 0 } else { rng.gen_range(0..3) } {
+            0 => Move::Insert {
+                process: rng.gen_range(0..model.processes.len()),
+                count: 1 << rng.gen_range(0..8),
+                start: at,
+                position: rng.gen_range(0..=self.log.len()),
+            },
+            1 => Move::Remove { entry: rng.gen_range(0..self.log.len()) },
+            _ => Move::Shift { entry: rng.gen_range(0..self.log.len()), start: at },
+        }
+    }
+
+    /// The (process, time slot) pairs a move touches: where a run appears and
+    /// where one disappears.
+    fn attributes(&self, model: &Model, bucket: u64, edit: Move) -> Vec<(usize, u64)> {
+        let process = |entry: usize| model.processes.iter().position(|step| step.id == self.log[entry].0).unwrap_or(0);
+        match edit {
+            Move::Insert { process, start, .. } => vec![(process, start / bucket)],
+            Move::Remove { entry } => vec![(process(entry), self.log[entry].2 / bucket)],
+            Move::Shift { entry, start } => vec![(process(entry), self.log[entry].2 / bucket), (process(entry), start / bucket)],
+        }
+    }
+
+    fn apply(&self, data: &Data, model: &Model, edit: Move) -> Self {
+        let mut log = self.log.clone();
+        match edit {
+            Move::Insert { process, count, start, position } => log.insert(position, (model.processes[process].id.clone(), count, start)),
+            Move::Remove { entry } => {
+                log.remove(entry);
+            }
+            Move::Shift { entry, start } => log[entry].2 = start,
+        }
+        /* where an insertion lands among runs of the same time decides which
+         * run gets the stocks first */
+        Schedule::new(data, log)
+    }
+}
+
+/// Tabu search over complete schedules. Every iteration samples insert,
+/// remove and shift moves, replays each through the simulator and takes the
+/// best one whose (process, time slot) attributes are not tabu, unless it beats
+/// the best schedule found (aspiration). Applied moves make their attributes
+/// tabu for a few iterations. When the best stalls, the search restarts from a
+/// perturbed copy of it with an empty tabu list.
+pub fn tabu_search(data: &Data, max_iterations: usize, delay: u32, progress: &Reporter) -> (HashMap<String, u64>, u64, Log) {
+    let timer_flag = delay::start_timer(std::time::Duration::from_secs(delay as u64));
+    let start = Instant::now();
+    let mut rng = rand::thread_rng();
+
+    let model = Model::new(data);
+    let bucket = ilp::default_bucket(data);
+    let idle = Schedule::new(data, Vec::new());
+    let seeded = Schedule::new(data, sgs::greedy_log(data));
+    let mut current = if seeded.better_than(&idle) { seeded } else { idle };
+    let mut best = current.clone();
+
+    if model.processes.is_empty() {
+        return (best.stocks, best.makespan, best.log);
+    }
+
+    /* attribute -> first iteration where it is allowed again */
+    let mut tabu: HashMap<(usize, u64), u64> = HashMap::new();
+    let mut iterations: u64 = 0;
+    let mut last_best: u64 = 0;
+    let mut restarts: u64 = 0;
+
+    while (iterations as usize) < max_iterations {
+        if timer_flag.load(AtomicOrdering::SeqCst) {
+            say!("Timer elapsed, stopping optimization");
+            break;
+        }
+        iterations += 1;
+
+        if iterations - last_best > STALL {
+            current = best.clone();
+            for _ in 0..PERTURBATION {
+                let times = current.event_times(data);
+                let edit = current.random_move(&model, &times, &mut rng);
+                current = current.apply(data, &model, edit);
+            }
+            tabu.clear();
+            last_best = iterations;
+            restarts += 1;
+        }
+
+        let times = current.event_times(data);
+        let mut chosen: Option<(Schedule, Vec<(usize, u64)>)> = None;
+        for _ in 0..CANDIDATES {
+            let edit = current.random_move(&model, &times, &mut rng);
+            let attributes = current.attributes(&model, bucket, edit);
+            let candidate = current.apply(data, &model, edit);
+            /* the replay dropped or postponed the edit away: not a move */
+            if candidate.log == current.log {
+                continue;
+            }
+            let forbidden = attributes.iter().any(|attribute| tabu.get(attribute).is_some_and(|&until| until > iterations));
+            if forbidden && !candidate.better_than(&best) {
+                continue;
+            }
+            if chosen.as_ref().is_none_or(|(other, _)| candidate.energy < other.energy) {
+                chosen = Some((candidate, attributes));
+            }
+        }
+
+        /* every sampled move was tabu: keep the schedule and let tenures expire */
+        let Some((next, attributes)) = chosen else {
+            continue;
+        };
+        let tenure = TENURE + rng.gen_range(0..=model.processes.len() as u64);
+        for attribute in attributes {
+            tabu.insert(attribute, iterations + tenure);
+        }
+        if tabu.len() > MAX_TABU {
+            tabu.retain(|_, until| *until > iterations);
+        }
+        current = next;
+        if current.better_than(&best) {
+            best = current.clone();
+            last_best = iterations;
+        }
+
+        progress.tick(iterations, best.objective, best.makespan, || format!("tabu {} restarts {}", tabu.len(), restarts));
+    }
+
+    progress.finish(iterations, best.objective, best.makespan, format!("{} restarts", restarts));
+
+    let elapsed = start.elapsed();
+    say!("Tabu Search executed in: {}.{:03} seconds\n", elapsed.as_secs(), elapsed.subsec_millis());
+
+    (best.stocks, best.makespan, best.log)
+}
@@ -3,6 +3,7 @@ pub enum Token {
     Identifier(String),
     Number(u64),
     Optimize,
+    Horizon,
     Colon,
     Semicolon,
     LeftParen,